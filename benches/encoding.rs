@@ -0,0 +1,65 @@
+//! Compares the bit packed and mixed-radix state encodings, run with `cargo bench --no-default-features`
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use emath::{Pos2, Rect, Vec2};
+use graph_explorer::graph::GraphType;
+use graph_explorer::state::{Classification, Encoding, MoveRule, Progress, StateSpace, explore};
+
+// Each measurement keeps the fastest of this many runs
+const RUNS: usize = 3;
+
+// (family spec, max), a mix of bases which are and aren't powers of two
+const CASES: [(&str, u8); 5] = [
+  ("cycle:10", 2),
+  ("grid:2,4", 4),
+  ("petersen", 2),
+  ("wheel:6", 5),
+  ("complete:6", 3),
+];
+
+fn fastest(mut run: impl FnMut()) -> Duration {
+  (0 .. RUNS).map(|_| {
+    let start = Instant::now();
+    run();
+    start.elapsed()
+  }).min().unwrap()
+}
+
+fn explore_with(neighbors: &[Vec<usize>], max: u8, encoding: Encoding) -> Box<dyn StateSpace> {
+  let maxes = vec![max; neighbors.len()];
+  explore(neighbors.to_vec(), maxes, MoveRule::default(), encoding, false, None, None, &Progress::default()).unwrap()
+}
+
+// Every move from every valid state, which is mostly digit gets and sets
+fn splash_all(state_space: &dyn StateSpace) {
+  for idx in 0 .. state_space.list_len(Classification::Valid) {
+    let state = state_space.list_state(Classification::Valid, idx).unwrap();
+    for center in 0 .. state_space.length() {
+      for operation in [-1, 1] { black_box(state_space.splash_state(state, center, operation, false)); }
+    }
+  }
+}
+
+fn main() {
+  println!("{:<12} {:>3} {:>10} {:>10} {:>10} {:>10}", "graph", "max", "explore", "", "splash", "");
+  println!("{:<12} {:>3} {:>10} {:>10} {:>10} {:>10}", "", "", "bits", "radix", "bits", "radix");
+  for (spec, max) in CASES {
+    let mut graph = GraphType::from_spec(spec).unwrap().build(Rect::from_min_size(Pos2::ZERO, Vec2::splat(1000.)));
+    graph.contiguize_and_trim();
+    let neighbors = graph.get_neighbors();
+
+    let [explore_bits, explore_radix] = Encoding::ALL.map(|encoding| fastest(|| {
+      black_box(explore_with(&neighbors, max, encoding));
+    }));
+    let [splash_bits, splash_radix] = Encoding::ALL.map(|encoding| {
+      let state_space = explore_with(&neighbors, max, encoding);
+      fastest(|| splash_all(state_space.as_ref()))
+    });
+    println!(
+      "{spec:<12} {max:>3} {:>10.2?} {:>10.2?} {:>10.2?} {:>10.2?}",
+      explore_bits, explore_radix, splash_bits, splash_radix,
+    );
+  }
+}
@@ -0,0 +1,236 @@
+//! Headless analyzer, prints state space statistics for a graph without opening a window
+
+use std::process::ExitCode;
+
+use emath::{Pos2, Rect, Vec2};
+use graph_explorer::graph::{GraphType, from_digraph6, from_graph6};
+use graph_explorer::state::{AnyState, Boundary, Cache, Classification, Encoding, MoveRule, Neighborhood, Progress, StateSpace, explore};
+
+const USAGE: &str = "\
+Usage: graph-analyzer <GRAPH> [--max N] [--maxes A,B,..] [--open] [--center D] [--neighbor D] [--boundary B] [--orbits] [--encoding E] [--cache DIR] [--threads N] [--json]
+
+GRAPH is either a graph6 or digraph6 string or a family spec like cycle:7 or grid:3,4
+Families: path:n, cycle:n, complete:n, wheel:n, star:n, grid:rows,columns, ladder:n, prism:n,
+hypercube:dimension, bipartite:m,n, petersen, gpetersen:n,k, friendship:n, tree:n[,seed]
+--max defaults to 2 and goes up to 9, --maxes gives every node its own max instead
+--open leaves the center of a move alone, --center and --neighbor set how much a move adds, 1 by default
+--boundary is what happens past 0 or max, one of clamp (default), wrap like Lights Out, or reject the move
+--orbits counts one state per orbit of the graph's automorphisms
+--encoding is how states get packed, bits (default) or radix which fits more nodes when max + 1 isn't a power of two
+--cache reads the state space from DIR if it's been explored before, and saves it there otherwise
+--threads sets how many threads explore, by default one for small graphs and every core otherwise";
+
+struct Args {
+  graph: String,
+  max: u8,
+  // One per node, overriding max
+  maxes: Option< Vec<u8> >,
+  rule: MoveRule,
+  encoding: Encoding,
+  orbits: bool,
+  cache: Option<Cache>,
+  threads: Option<usize>,
+  json: bool,
+}
+impl Args {
+  fn parse() -> Result<Self, String> {
+    let mut graph = None;
+    let mut max = 2;
+    let mut maxes = None;
+    let mut rule = MoveRule::default();
+    let mut encoding = Encoding::default();
+    let mut orbits = false;
+    let mut cache = None;
+    let mut threads = None;
+    let mut json = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--json" => json = true,
+        "--open" => rule.neighborhood = Neighborhood::Open,
+        "--center" => rule.center_delta = delta(args.next(), "--center")?,
+        "--neighbor" => rule.neighbor_delta = delta(args.next(), "--neighbor")?,
+        "--boundary" => {
+          let value = args.next().ok_or("--boundary needs a value")?;
+          rule.boundary = Boundary::ALL.into_iter()
+            .find(|boundary| boundary.to_string().to_lowercase() == value)
+            .ok_or(format!("Invalid boundary {value}"))?
+          ;
+        }
+        "--orbits" => orbits = true,
+        "--cache" => cache = Some(Cache::new(args.next().ok_or("--cache needs a directory")?)),
+        "--threads" => {
+          let value = args.next().ok_or("--threads needs a value")?;
+          threads = Some(value.parse().ok().filter(|threads| *threads > 0).ok_or(format!("Invalid threads {value}"))?);
+        }
+        "--encoding" => {
+          let value = args.next().ok_or("--encoding needs a value")?;
+          encoding = match value.as_str() {
+            "bits" => Encoding::BitPacked,
+            "radix" => Encoding::MixedRadix,
+            _ => return Err(format!("Invalid encoding {value}")),
+          };
+        }
+        "--max" => {
+          let value = args.next().ok_or("--max needs a value")?;
+          max = value.parse().ok().filter(|max| *max <= 9)
+            .ok_or(format!("Invalid max {value}"))?
+          ;
+        }
+        "--maxes" => {
+          let value = args.next().ok_or("--maxes needs a value")?;
+          maxes = Some(value.split(',')
+            .map(|max| max.parse().ok().filter(|max| *max <= 9))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or(format!("Invalid maxes {value}"))?
+          );
+        }
+        "-h" | "--help" => return Err(USAGE.to_string()),
+        _ if graph.is_none() => graph = Some(arg),
+        _ => return Err(format!("Unexpected argument {arg}")),
+      }
+    }
+
+    Ok(Self { graph: graph.ok_or("Missing graph")?, max, maxes, rule, encoding, orbits, cache, threads, json })
+  }
+}
+
+fn delta(value: Option<String>, flag: &str) -> Result<i8, String> {
+  let value = value.ok_or(format!("{flag} needs a value"))?;
+  value.parse().ok().filter(|delta: &i8| (-9 ..= 9).contains(delta)).ok_or(format!("Invalid delta {value}"))
+}
+
+/// Builds the graph from either a family spec or a graph6 or digraph6 string,
+/// a digraph's neighbors are its out-neighbors
+fn neighbors(graph: &str) -> Option< Vec< Vec<usize> > > {
+  if let Some(family) = GraphType::from_spec(graph) {
+    let mut graph = family.build(Rect::from_min_size(Pos2::ZERO, Vec2::splat(1000.)));
+    graph.contiguize_and_trim();
+    return Some(graph.get_neighbors())
+  }
+  from_digraph6(graph).or_else(|| from_graph6(graph))
+}
+
+struct Report {
+  nodes: usize,
+  max: u8,
+  maxes: Vec<u8>,
+  rule: MoveRule,
+  // Counts are of orbits rather than states
+  orbits: bool,
+  valid: usize,
+  theorems: Vec<(String, usize)>,
+  other: usize,
+  // Sizes of every bubble with more than one state
+  bubbles: Vec<usize>,
+  singletons: usize,
+  // Of the bubble DAG
+  groups: usize,
+  sources: usize,
+  sinks: usize,
+  longest_chain: usize,
+}
+impl Report {
+  fn new(state_space: &dyn StateSpace) -> Self {
+    let theorems = state_space.theorem_names().iter().enumerate().map(|(idx, name)| {
+      (name.to_string(), state_space.list_len(Classification::InvalidTheorem(idx)))
+    }).collect();
+    // The last bubble holds every bubble of size 1
+    let bubble_count = state_space.bubble_count();
+    let bubbles = (0 .. bubble_count - 1).map(|bubble| state_space.bubble_len(bubble)).collect();
+    let dag = state_space.bubble_dag();
+
+    Self {
+      nodes: state_space.length(),
+      max: state_space.base() - 1,
+      maxes: state_space.bases().iter().map(|base| base - 1).collect(),
+      rule: state_space.rule(),
+      orbits: state_space.orbit_size(AnyState::default()).is_some(),
+      valid: state_space.list_len(Classification::Valid),
+      theorems,
+      other: state_space.list_len(Classification::InvalidOther),
+      bubbles,
+      singletons: state_space.bubble_len(bubble_count - 1),
+      groups: dag.groups.len(),
+      sources: dag.sources.len(),
+      sinks: dag.sinks.len(),
+      longest_chain: dag.longest_chain.len(),
+    }
+  }
+
+  fn maxes_list(&self) -> String { self.maxes.iter().map(u8::to_string).collect::<Vec<_>>().join(",") }
+
+  fn largest_bubble(&self) -> usize { self.bubbles.iter().copied().max().unwrap_or(0) }
+
+  fn print_human(&self) {
+    println!("{} nodes, max {}{}", self.nodes, self.max, if self.orbits { ", counting orbits" } else { "" });
+    if self.maxes.iter().any(|max| *max != self.max) { println!("Maxes: {}", self.maxes_list()) }
+    println!("Rule: {}", self.rule);
+    println!("Valid: {}", self.valid);
+    for (name, count) in &self.theorems {
+      println!("Invalid, {name}: {count}");
+    }
+    println!("Invalid, Other: {}", self.other);
+    println!("Bubbles: {}, largest {}", self.bubbles.len(), self.largest_bubble());
+    println!("Size 1 bubbles: {}", self.singletons);
+    println!(
+      "Bubble DAG: {} groups, {} sources, {} sinks, longest chain {}",
+      self.groups, self.sources, self.sinks, self.longest_chain
+    );
+  }
+
+  fn print_json(&self) {
+    let theorems = self.theorems.iter()
+      .map(|(name, count)| format!("{{\"name\":\"{}\",\"count\":{count}}}", escape(name)))
+      .collect::<Vec<_>>()
+      .join(",")
+    ;
+    let sizes = self.bubbles.iter().map(usize::to_string).collect::<Vec<_>>().join(",");
+    println!(
+      "{{\"nodes\":{},\"max\":{},\"maxes\":[{}],\"rule\":{{\"neighborhood\":\"{}\",\"center\":{},\"neighbor\":{},\"boundary\":\"{}\"}},\"orbits\":{},\"valid\":{},\"invalid\":{{\"theorems\":[{theorems}],\"other\":{}}},\
+      \"bubbles\":{{\"count\":{},\"largest\":{},\"singletons\":{},\"sizes\":[{sizes}]}},\
+      \"dag\":{{\"groups\":{},\"sources\":{},\"sinks\":{},\"longest_chain\":{}}}}}",
+      self.nodes, self.max, self.maxes_list(),
+      self.rule.neighborhood.to_string().to_lowercase(), self.rule.center_delta, self.rule.neighbor_delta,
+      self.rule.boundary.to_string().to_lowercase(), self.orbits, self.valid, self.other,
+      self.bubbles.len(), self.largest_bubble(), self.singletons,
+      self.groups, self.sources, self.sinks, self.longest_chain,
+    );
+  }
+}
+
+fn escape(str: &str) -> String {
+  str.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn main() -> ExitCode {
+  let args = match Args::parse() {
+    Ok(args) => args,
+    Err(message) => {
+      eprintln!("{message}");
+      return ExitCode::FAILURE;
+    }
+  };
+
+  let Some(neighbors) = neighbors(&args.graph) else {
+    eprintln!("{} isn't a graph6 or digraph6 string or a family spec\n\n{USAGE}", args.graph);
+    return ExitCode::FAILURE;
+  };
+  let maxes = args.maxes.unwrap_or_else(|| vec![args.max; neighbors.len()]);
+  if maxes.len() != neighbors.len() {
+    eprintln!("--maxes has {} entries but the graph has {} nodes", maxes.len(), neighbors.len());
+    return ExitCode::FAILURE;
+  }
+  let empty = neighbors.is_empty();
+  let cache = args.cache.as_ref();
+  let Some(state_space) = explore(neighbors, maxes, args.rule, args.encoding, args.orbits, cache, args.threads, &Progress::default()) else {
+    // Nothing cancels the progress, so that's the only other way it can fail
+    eprintln!("{}", if empty { "Graph has no nodes" } else { "Graph has too many states to index" });
+    return ExitCode::FAILURE;
+  };
+
+  let report = Report::new(state_space.as_ref());
+  if args.json { report.print_json() } else { report.print_human() }
+  ExitCode::SUCCESS
+}
@@ -1,21 +1,60 @@
-use eframe::egui::{Color32, Pos2};
+mod automorphism;
+mod family;
+mod graph6;
+mod invariants;
+pub mod layout;
+pub mod ops;
+mod project;
+
+use std::collections::HashMap;
+
+use ecolor::Color32;
+use emath::Pos2;
 use lilypads::Pond;
 
+pub use automorphism::{AutomorphismGroup, canonical_labeling};
+pub use family::{GraphType, grid_points, points_on_circle, row_points};
+pub use graph6::{from_digraph6, from_graph6, to_digraph6, to_graph6};
+pub use invariants::Invariants;
+pub use project::{from_project, to_project};
+
+pub const NODE_RADIUS: f32 = 40.;
+// Distance between neighboring nodes in rows and grids
+const NODE_SPACING: f32 = NODE_RADIUS * 3.;
+
+#[derive(Debug)]
 pub struct Graph { 
   pub nodes: Pond<Node>,
+  // Neighbors are out-neighbors, and connections only go one way
+  pub directed: bool,
+}
+// Pond isn't Clone, so copy it slot by slot
+impl Clone for Graph {
+  fn clone(&self) -> Self {
+    let mut nodes = Pond::new();
+    for (idx, node) in self.nodes.iter() { nodes.write(idx, node.clone()); }
+    Self { nodes, directed: self.directed }
+  }
+}
+impl Default for Graph {
+  fn default() -> Self { Self::new() }
 }
 impl Graph {
-  pub fn new() -> Self { Self { nodes: Pond::new() } }
+  pub fn new() -> Self { Self { nodes: Pond::new(), directed: false } }
   
   pub fn add_node(&mut self, position: Pos2) -> usize {
     self.nodes.insert(Node::new(position))
   }
 
-  /// Adds neighbors if not neighbors already, otherwise does nothing
+  /// Adds neighbors if not neighbors already, otherwise does nothing.
+  /// Only adds the arc from node1 to node2 in a directed graph.
   /// True on success, false on failure
   pub fn attempt_unique_connection(&mut self, node1: usize, node2: usize) -> bool {
+    // A node can't neighbor itself, splash would hit it twice
+    if node1 == node2 { return false }
     if self.nodes.is_occupied(node1) && self.nodes.is_occupied(node2) {
       if !self.nodes.get_mut(node1).unwrap().add_unique_neighbor(node2) { return false };
+      if self.directed { return true }
       if !self.nodes.get_mut(node2).unwrap().add_unique_neighbor(node1) { return false };
       true
     } else { false }
@@ -27,9 +66,11 @@ impl Graph {
     } else { false }
   }
 
+  /// Only removes the arc from node1 to node2 in a directed graph
   pub fn remove_connection(&mut self, node1: usize, node2: usize) -> bool {
     if self.nodes.is_occupied(node1) && self.nodes.is_occupied(node2) {
       if !self.nodes.get_mut(node1).unwrap().remove_neighbor(node2) { return false };
+      if self.directed { return true }
       if !self.nodes.get_mut(node2).unwrap().remove_neighbor(node1) { return false };
       true
     } else { false }
@@ -57,19 +98,46 @@ impl Graph {
     output
   }
 
-  pub fn remove(&mut self, removed: usize) {
+  /// Nodes with an arc to `node`, the same as its neighbors when undirected
+  pub fn in_neighbors(&self, node: usize) -> Vec<usize> {
+    if !self.directed { return self.nodes.get(node).map_or(Vec::new(), |node| node.neighbors.clone()) }
+    self.nodes.iter().filter(|(_, other)| other.has_neighbor(node)).map(|(idx, _)| idx).collect()
+  }
+
+  /// Returns the removed node, which [Self::insert_at] can put back
+  pub fn remove(&mut self, removed: usize) -> Node {
+    let in_neighbors = self.in_neighbors(removed);
     let removed_node = self.nodes.free(removed).unwrap();
-    for neighbor in removed_node.neighbors {
+    for neighbor in in_neighbors {
       self.nodes.get_mut(neighbor).unwrap().neighbors.retain(|search| { *search != removed });
     }
+    removed_node
+  }
+
+  /// Writes `node` into the slot `idx` and connects `in_neighbors` back to it
+  pub fn insert_at(&mut self, idx: usize, node: Node, in_neighbors: &[usize]) {
+    for neighbor in in_neighbors {
+      if let Some(neighbor) = self.nodes.get_mut(*neighbor) { neighbor.add_unique_neighbor(idx); }
+    }
+    self.nodes.write(idx, node);
+  }
+
+  /// Adds the reverse of every arc, leaving an undirected graph
+  pub fn make_undirected(&mut self) {
+    self.directed = false;
+    let arcs = self.nodes.iter()
+      .flat_map(|(idx, node)| node.neighbors.iter().map(move |neighbor| (idx, *neighbor)))
+      .collect::<Vec<_>>()
+    ;
+    for (idx, neighbor) in arcs { self.nodes.get_mut(neighbor).unwrap().add_unique_neighbor(idx); }
   }
 
   pub fn node_at(&self, point: Pos2, radius: f32) -> Option<usize> {
     for (idx, node) in self.nodes.safe_data().iter().enumerate() {
-      if let Some(node) = *node {
-        if node.position.distance_sq(point) < (radius as i32).pow(2) as f32 {
-          return Some(idx)
-        }
+      if let Some(node) = *node
+        && node.position.distance_sq(point) < (radius as i32).pow(2) as f32
+      {
+        return Some(idx)
       }
     }
     None
@@ -78,13 +146,21 @@ impl Graph {
 }
 impl Graph {
 
+  /// Clamps every node to its own max, `max` for nodes without one
   pub fn correct_max(&mut self, max: u8) {
     for (_, node) in self.nodes.iter_mut() {
-      node.value = node.value.min(max);
+      node.value = node.value.min(node.max.unwrap_or(max));
     }
   }
 
-  pub fn contiguize_and_trim(&mut self) {
+  /// Each node's own max, `max` for nodes without one.
+  /// Assumes graph has already been contiguized by [Self::contiguize_and_trim]
+  pub fn get_maxes(&self, max: u8) -> Vec<u8> {
+    self.nodes.iter().map(|(_, node)| node.max.unwrap_or(max)).collect()
+  }
+
+  /// Returns which nodes moved, (Key: Old, Value: New)
+  pub fn contiguize_and_trim(&mut self) -> HashMap<usize, usize> {
     let fix = self.nodes.trim();
     for idx in 0 .. self.nodes.len() {
       let node = self.nodes.get_mut(idx).unwrap();
@@ -94,6 +170,7 @@ impl Graph {
       }
       node.neighbors = new_neighbors;
     }
+    fix
   }
 
   /// Assumes graph has already been contiguized by [Self::contiguize]
@@ -107,11 +184,24 @@ impl Graph {
 
 }
 
-#[derive(Debug)]
+/// The underlying undirected graph of out-neighbor lists, every arc going both ways
+pub fn underlying(neighbors: &[Vec<usize>]) -> Vec< Vec<usize> > {
+  let mut underlying = neighbors.to_vec();
+  for (node, neighbors) in neighbors.iter().enumerate() {
+    for neighbor in neighbors {
+      if !underlying[*neighbor].contains(&node) { underlying[*neighbor].push(node) }
+    }
+  }
+  underlying
+}
+
+#[derive(Clone, Debug)]
 pub struct Node {
   pub position: Pos2,
   pub neighbors: Vec<usize>,
   pub value: u8,
+  // Overrides the graph's max for this node
+  pub max: Option<u8>,
   pub color: Color32,
 }
 impl Node {
@@ -120,6 +210,7 @@ impl Node {
       position,
       neighbors: Vec::with_capacity(0),
       value: 0,
+      max: None,
       color: Color32::RED,
     }
   }
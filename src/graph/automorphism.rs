@@ -0,0 +1,269 @@
+//! Automorphisms and canonical labelings found by individualizing nodes and refining colors, like a very small nauty.
+//! The search gives up after [SEARCH_BUDGET] steps.
+
+use ahash::AHashMap;
+
+// Refinements the search gets before it gives up
+const SEARCH_BUDGET: usize = 200_000;
+
+/// Every automorphism of a graph, as generators
+#[derive(Clone, Debug)]
+pub struct AutomorphismGroup {
+  /// Each maps node -> image, every automorphism is a product of these
+  pub generators: Vec< Vec<usize> >,
+  /// None if it doesn't fit in a u128
+  pub size: Option<u128>,
+}
+impl AutomorphismGroup {
+  /// Expects the neighbors of a contiguized graph, None if the search gave up
+  pub fn new(neighbors: &[Vec<usize>]) -> Option<Self> {
+    Self::with_colors(neighbors, &vec![0; neighbors.len()])
+  }
+
+  /// Only the automorphisms taking every node to one of the same color
+  pub fn with_colors(neighbors: &[Vec<usize>], colors: &[usize]) -> Option<Self> {
+    let n = neighbors.len();
+    // Renumbered from 0 so fixed nodes can take the colors after them
+    let mut palette = colors.to_vec();
+    palette.sort_unstable();
+    palette.dedup();
+    let initial = colors.iter().map(|color| palette.binary_search(color).unwrap()).collect::<Vec<_>>();
+    let mut budget = SEARCH_BUDGET;
+    let mut generators = Vec::new();
+    let mut size = Some(1u128);
+    let mut fixed = Vec::new();
+    // Orbit-stabilizer, the group's size is the product of each node's orbit
+    // under the automorphisms fixing every node before it
+    for node in 0 .. n {
+      // Once the fixed nodes tell every node apart, only the identity is left
+      let mut colors = pair_colors(&initial, &fixed, None);
+      refine(neighbors, &mut colors);
+      if color_count(&colors[.. n]) == n { break }
+
+      // Orbits of the automorphisms found so far, anything already joined to node is free
+      let mut orbit_of = (0 .. n).collect::<Vec<_>>();
+      let mut orbit = 1;
+      for image in node + 1 .. n {
+        if find(&mut orbit_of, image) == find(&mut orbit_of, node) {
+          orbit += 1;
+          continue
+        }
+        let colors = pair_colors(&initial, &fixed, Some((node, image)));
+        let Some(mapping) = automorphism(neighbors, colors, &mut budget)? else { continue };
+        orbit += 1;
+        for (from, to) in mapping.iter().enumerate() {
+          let (from, to) = (find(&mut orbit_of, from), find(&mut orbit_of, *to));
+          orbit_of[from] = to;
+        }
+        generators.push(mapping);
+      }
+      size = size.and_then(|size| size.checked_mul(orbit));
+      fixed.push(node);
+    }
+    Some(Self { generators, size })
+  }
+}
+
+/// Numbers the nodes so that relabeling the graph doesn't change the result, maps node -> its number.
+/// Isomorphic graphs whose isomorphism keeps `colors` end up with the same neighbors once renumbered.
+/// None if the search gave up
+pub fn canonical_labeling(neighbors: &[Vec<usize>], colors: &[usize]) -> Option< Vec<usize> > {
+  // Subtrees of the search that an automorphism swaps give the same labelings
+  let group = AutomorphismGroup::with_colors(neighbors, colors)?;
+  let mut search = LabelingSearch { neighbors, generators: &group.generators, budget: SEARCH_BUDGET, best: None };
+  search.search(colors.to_vec(), &mut Vec::new())?;
+  search.best.map(|(_, labeling)| labeling)
+}
+
+// Individualizes one node at a time like the automorphism search, but tries every node of the cell
+// and keeps the labeling whose renumbered neighbors come first
+struct LabelingSearch<'a> {
+  neighbors: &'a [Vec<usize>],
+  generators: &'a [Vec<usize>],
+  budget: usize,
+  // The renumbered neighbors and the labeling
+  best: Option<(Vec< Vec<usize> >, Vec<usize>)>,
+}
+impl LabelingSearch<'_> {
+  fn search(&mut self, mut colors: Vec<usize>, individualized: &mut Vec<usize>) -> Option<()> {
+    self.budget = self.budget.checked_sub(1)?;
+    refine(self.neighbors, &mut colors);
+    let n = self.neighbors.len();
+    let mut sizes = vec![0; n];
+    for color in &colors { sizes[*color] += 1 }
+    // Every node has its own color, which is its number
+    let Some(cell) = (0 .. n).find(|color| sizes[*color] > 1) else {
+      let mut renumbered = vec![Vec::new(); n];
+      for (node, neighbors) in self.neighbors.iter().enumerate() {
+        renumbered[colors[node]] = neighbors.iter().map(|neighbor| colors[*neighbor]).collect();
+        renumbered[colors[node]].sort_unstable();
+      }
+      if self.best.as_ref().is_none_or(|(best, _)| renumbered < *best) { self.best = Some((renumbered, colors)) }
+      return Some(())
+    };
+
+    // Only the automorphisms fixing every individualized node keep the colors so far
+    let mut orbit_of = (0 .. n).collect::<Vec<_>>();
+    let stabilizer = self.generators.iter().filter(|generator| individualized.iter().all(|node| generator[*node] == *node));
+    for generator in stabilizer {
+      for (from, to) in generator.iter().enumerate() {
+        let (from, to) = (find(&mut orbit_of, from), find(&mut orbit_of, *to));
+        orbit_of[from] = to;
+      }
+    }
+    let mut tried = Vec::new();
+    for node in (0 .. n).filter(|node| colors[*node] == cell) {
+      let orbit = find(&mut orbit_of, node);
+      if tried.contains(&orbit) { continue }
+      tried.push(orbit);
+      // The node goes right before the rest of its cell
+      let mut next = colors.iter().map(|color| 2 * color + 1).collect::<Vec<_>>();
+      next[node] = 2 * cell;
+      individualized.push(node);
+      self.search(next, individualized)?;
+      individualized.pop();
+    }
+    Some(())
+  }
+}
+
+fn find(parents: &mut [usize], node: usize) -> usize {
+  let mut root = node;
+  while parents[root] != root { root = parents[root] }
+  parents[node] = root;
+  root
+}
+
+// Colors for two copies of the graph side by side, nodes n .. 2n are the second copy.
+// Fixed nodes get their own color on both sides, and so does the (node, image) pair
+fn pair_colors(initial: &[usize], fixed: &[usize], pair: Option<(usize, usize)>) -> Vec<usize> {
+  let n = initial.len();
+  let first = initial.iter().max().map_or(0, |color| color + 1);
+  let mut colors = initial.repeat(2);
+  for (idx, node) in fixed.iter().enumerate() {
+    colors[*node] = first + idx;
+    colors[n + node] = first + idx;
+  }
+  if let Some((node, image)) = pair {
+    colors[node] = first + fixed.len();
+    colors[n + image] = first + fixed.len();
+  }
+  colors
+}
+
+// Splits colors by their neighbors' colors until nothing changes. Colors get numbered in order of their
+// old color and their neighbors' colors, so they don't depend on the node order.
+// With two copies side by side, false once the copies can't match
+fn refine(neighbors: &[Vec<usize>], colors: &mut Vec<usize>) -> bool {
+  let n = neighbors.len();
+  let mut count = color_count(colors);
+  loop {
+    let signatures = (0 .. colors.len()).map(|node| {
+      let offset = node / n * n;
+      let mut around = neighbors[node % n].iter().map(|neighbor| colors[offset + neighbor]).collect::<Vec<_>>();
+      around.sort_unstable();
+      (colors[node], around)
+    }).collect::<Vec<_>>();
+    let mut palette = signatures.iter().collect::<Vec<_>>();
+    palette.sort_unstable();
+    palette.dedup();
+    *colors = signatures.iter().map(|signature| palette.binary_search(&signature).unwrap()).collect();
+
+    if colors.len() > n {
+      let mut balance = vec![0isize; palette.len()];
+      for color in &colors[.. n] { balance[*color] += 1 }
+      for color in &colors[n ..] { balance[*color] -= 1 }
+      if balance.iter().any(|balance| *balance != 0) { return false }
+    }
+
+    if palette.len() == count { return true }
+    count = palette.len();
+  }
+}
+
+fn color_count(colors: &[usize]) -> usize {
+  let mut seen = colors.to_vec();
+  seen.sort_unstable();
+  seen.dedup();
+  seen.len()
+}
+
+// Individualizes one node at a time until the coloring pins down a mapping, None if out of budget
+fn automorphism(neighbors: &[Vec<usize>], mut colors: Vec<usize>, budget: &mut usize) -> Option< Option< Vec<usize> > > {
+  *budget = budget.checked_sub(1)?;
+  if !refine(neighbors, &mut colors) { return Some(None) }
+  let n = neighbors.len();
+
+  let mut sizes = AHashMap::new();
+  for color in &colors[.. n] { *sizes.entry(*color).or_insert(0) += 1 }
+  let Some(node) = (0 .. n).find(|node| sizes[&colors[*node]] > 1) else {
+    let mut image_of_color = AHashMap::new();
+    for image in 0 .. n { image_of_color.insert(colors[n + image], image); }
+    let mapping = (0 .. n).map(|node| image_of_color[&colors[node]]).collect::<Vec<_>>();
+    let preserves_edges = (0 .. n).all(|node| {
+      neighbors[node].iter().all(|neighbor| neighbors[mapping[node]].contains(&mapping[*neighbor]))
+    });
+    return Some(preserves_edges.then_some(mapping))
+  };
+
+  // Refined colors are all below 2n
+  let fresh = 2 * n;
+  for image in (0 .. n).filter(|image| colors[n + image] == colors[node]) {
+    let mut next = colors.clone();
+    next[node] = fresh;
+    next[n + image] = fresh;
+    if let Some(mapping) = automorphism(neighbors, next, budget)? { return Some(Some(mapping)) }
+  }
+  Some(None)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::canonical_labeling;
+
+  fn renumbered(neighbors: &[Vec<usize>], colors: &[usize]) -> (Vec< Vec<usize> >, Vec<usize>) {
+    let numbers = canonical_labeling(neighbors, colors).unwrap();
+    let mut renumbered = (vec![Vec::new(); neighbors.len()], vec![0; colors.len()]);
+    for (node, list) in neighbors.iter().enumerate() {
+      renumbered.0[numbers[node]] = list.iter().map(|neighbor| numbers[*neighbor]).collect();
+      renumbered.0[numbers[node]].sort_unstable();
+      renumbered.1[numbers[node]] = colors[node];
+    }
+    renumbered
+  }
+
+  #[test]
+  fn relabeling_keeps_canonical_form() {
+    let graphs = [
+      // Petersen, a tree, a directed cycle with a chord, and two components
+      vec![
+        vec![1, 4, 5], vec![0, 2, 6], vec![1, 3, 7], vec![2, 4, 8], vec![3, 0, 9],
+        vec![0, 7, 8], vec![1, 8, 9], vec![2, 9, 5], vec![3, 5, 6], vec![4, 6, 7],
+      ],
+      vec![vec![1, 2], vec![0, 3, 4], vec![0, 5], vec![1], vec![1], vec![2]],
+      vec![vec![1], vec![2], vec![3, 0], vec![4], vec![0]],
+      vec![vec![1], vec![0, 2], vec![1], vec![4], vec![3]],
+    ];
+    for neighbors in &graphs {
+      let n = neighbors.len();
+      for colors in [vec![0; n], (0 .. n).map(|node| node % 2).collect()] {
+        let canonical = renumbered(neighbors, &colors);
+        // Reversing, rotating, and both
+        let relabelings: [&dyn Fn(usize) -> usize; 3] = [
+          &|node| n - 1 - node,
+          &|node| (node + 1) % n,
+          &|node| (n - node) % n,
+        ];
+        for image in relabelings {
+          let mut relabeled = vec![Vec::new(); n];
+          let mut relabeled_colors = vec![0; n];
+          for (node, list) in neighbors.iter().enumerate() {
+            relabeled[image(node)] = list.iter().map(|neighbor| image(*neighbor)).collect();
+            relabeled_colors[image(node)] = colors[node];
+          }
+          assert_eq!(renumbered(&relabeled, &relabeled_colors), canonical, "{neighbors:?} with colors {colors:?}");
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,263 @@
+use std::f32::consts::{PI, TAU};
+
+use emath::{Pos2, Rect, Vec2};
+
+use super::{Graph, NODE_RADIUS, NODE_SPACING};
+
+pub enum GraphType {
+  Path(usize),
+  Cycle(usize),
+  Complete(usize),
+  Wheel(usize),
+  Star(usize),
+  // (rows, columns)
+  Grid(usize, usize),
+  Ladder(usize),
+  Prism(usize),
+  // Dimension
+  Hypercube(usize),
+  CompleteBipartite(usize, usize),
+  Petersen,
+  // (n, k) with n outer nodes, and inner node i connected to i + k
+  GeneralizedPetersen(usize, usize),
+  // Triangle count
+  Friendship(usize),
+  // (n, seed)
+  RandomTree(usize, u64),
+  Imported(Vec< Vec<usize> >),
+  // Out-neighbors, from digraph6
+  ImportedDirected(Vec< Vec<usize> >),
+}
+impl GraphType {
+  /// Parses a family spec like `cycle:7` or `grid:3,4`
+  pub fn from_spec(spec: &str) -> Option<Self> {
+    let (family, params) = spec.split_once(':').unwrap_or((spec, ""));
+    let params = params.split(',')
+      .filter(|param| !param.trim().is_empty())
+      .map(|param| param.trim().parse().ok())
+      .collect::<Option< Vec<usize> >>()?
+    ;
+    let family = match (family.trim().to_lowercase().as_str(), params.as_slice()) {
+      ("path", &[n]) => Self::Path(n),
+      ("cycle", &[n]) => Self::Cycle(n),
+      ("complete", &[n]) => Self::Complete(n),
+      ("wheel", &[n]) => Self::Wheel(n),
+      ("star", &[n]) => Self::Star(n),
+      ("grid", &[rows, columns]) => Self::Grid(rows, columns),
+      ("ladder", &[n]) => Self::Ladder(n),
+      ("prism", &[n]) => Self::Prism(n),
+      ("hypercube", &[dimension]) => Self::Hypercube(dimension),
+      ("bipartite", &[m, n]) => Self::CompleteBipartite(m, n),
+      ("petersen", &[]) => Self::Petersen,
+      ("gpetersen", &[n, k]) => Self::GeneralizedPetersen(n, k),
+      ("friendship", &[n]) => Self::Friendship(n),
+      ("tree", &[n]) => Self::RandomTree(n, 0),
+      ("tree", &[n, seed]) => Self::RandomTree(n, seed as u64),
+      _ => return None,
+    };
+    family.is_valid().then_some(family)
+  }
+
+  /// False if the parameters don't describe a graph with at least one node
+  pub fn is_valid(&self) -> bool {
+    match *self {
+      Self::Path(n) | Self::Cycle(n) | Self::Complete(n) | Self::Wheel(n) | Self::Star(n)
+        | Self::Ladder(n) | Self::Friendship(n) | Self::RandomTree(n, _) => n > 0,
+      Self::Grid(m, n) | Self::CompleteBipartite(m, n) => m > 0 && n > 0,
+      Self::Prism(n) => n >= 3,
+      // Doubles with each dimension, 256 nodes is about the biggest grid the load menu builds
+      Self::Hypercube(dimension) => dimension <= 8,
+      Self::GeneralizedPetersen(n, k) => n >= 3 && k > 0 && 2 * k < n,
+      Self::Petersen | Self::Imported(_) | Self::ImportedDirected(_) => true,
+    }
+  }
+
+  // Add a max size to prevent from going off screen
+  pub fn build(self, space: Rect) -> Graph {
+    let center = space.center();
+    let directed = matches!(self, Self::ImportedDirected(_));
+    let (points, edges) = match self {
+      Self::Path(n) => (row_points(n, center), path_edges(0, n)),
+      Self::Cycle(n) => (points_on_circle(n, center, NODE_RADIUS * n as f32), cycle_edges(0, n)),
+      Self::Complete(n) => {
+        let edges = (0 .. n).flat_map(|i| (i + 1 .. n).map(move |j| (i, j))).collect();
+        (points_on_circle(n, center, NODE_RADIUS * n as f32), edges)
+      }
+      Self::Wheel(n) => {
+        let mut points = vec![center];
+        points.extend(points_on_circle(n, center, (NODE_RADIUS * n as f32).max(200.)));
+        let mut edges = cycle_edges(1, n);
+        edges.extend((1 ..= n).map(|rim| (0, rim)));
+        (points, edges)
+      }
+      Self::Star(n) => {
+        let mut points = vec![center];
+        points.extend(points_on_circle(n, center, (NODE_RADIUS * n as f32).max(200.)));
+        (points, (1 ..= n).map(|leaf| (0, leaf)).collect())
+      }
+      Self::Grid(rows, columns) => (grid_points(rows, columns, center), grid_edges(rows, columns)),
+      Self::Ladder(n) => (grid_points(2, n, center), grid_edges(2, n)),
+      Self::Prism(n) => generalized_petersen(n, 1, center),
+      Self::Hypercube(dimension) => {
+        // Each dimension pushes the node along its own direction, the usual drawing of a cube
+        let directions = (0 .. dimension)
+          .map(|bit| Vec2::angled(PI * bit as f32 / dimension as f32) * NODE_SPACING)
+          .collect::<Vec<_>>()
+        ;
+        let points = (0 .. 1usize << dimension).map(|node| {
+          directions.iter().enumerate().fold(center, |point, (bit, direction)| {
+            if node >> bit & 1 == 1 { point + *direction / 2. } else { point - *direction / 2. }
+          })
+        }).collect();
+        let edges = (0 .. 1usize << dimension)
+          .flat_map(|node| (0 .. dimension).map(move |bit| (node, node ^ 1 << bit)))
+          .filter(|(node, other)| node < other)
+          .collect()
+        ;
+        (points, edges)
+      }
+      Self::CompleteBipartite(m, n) => {
+        let gap = Vec2::new(0., NODE_SPACING);
+        let mut points = row_points(m, center - gap);
+        points.extend(row_points(n, center + gap));
+        (points, (0 .. m).flat_map(|i| (m .. m + n).map(move |j| (i, j))).collect())
+      }
+      Self::Petersen => generalized_petersen(5, 2, center),
+      Self::GeneralizedPetersen(n, k) => generalized_petersen(n, k, center),
+      Self::Friendship(n) => {
+        let mut points = vec![center];
+        points.extend(points_on_circle(2 * n, center, (NODE_RADIUS * 2. * n as f32).max(200.)));
+        let mut edges: Vec<_> = (1 ..= 2 * n).map(|outer| (0, outer)).collect();
+        edges.extend((0 .. n).map(|triangle| (2 * triangle + 1, 2 * triangle + 2)));
+        (points, edges)
+      }
+      Self::RandomTree(n, seed) => random_tree(n, seed, center),
+      Self::Imported(adj) | Self::ImportedDirected(adj) => {
+        // graph6 has no positions, so lay the nodes out on a circle that fits the screen
+        let n = adj.len();
+        let fit = (space.width().min(space.height()) / 2. - NODE_RADIUS).max(NODE_RADIUS);
+        let big_radius = (NODE_RADIUS * n as f32).min(fit);
+        let edges = adj.into_iter().enumerate()
+          .flat_map(|(node, neighbors)| neighbors.into_iter().map(move |neighbor| (node, neighbor)))
+          .collect()
+        ;
+        (points_on_circle(n, center, big_radius), edges)
+      }
+    };
+
+    let mut graph = Graph::new();
+    graph.directed = directed;
+    for point in points { graph.add_node(point); }
+    for (node1, node2) in edges { graph.attempt_unique_connection(node1, node2); }
+    graph
+  }
+}
+
+pub fn points_on_circle( n: usize, center: Pos2, radius: f32) -> Vec<Pos2> {
+  (0..n).map(|i| {
+    let theta = TAU * i as f32 / n as f32;
+    Pos2::new(
+      center.x + radius * theta.cos(),
+      center.y + radius * theta.sin(),
+    )
+  }).collect()
+}
+
+/// `n` points in a horizontal line centered on `center`
+pub fn row_points(n: usize, center: Pos2) -> Vec<Pos2> {
+  let start = center - Vec2::new(NODE_SPACING * n.saturating_sub(1) as f32 / 2., 0.);
+  (0 .. n).map(|i| start + Vec2::new(NODE_SPACING * i as f32, 0.)).collect()
+}
+
+/// Row major points of a grid centered on `center`
+pub fn grid_points(rows: usize, columns: usize, center: Pos2) -> Vec<Pos2> {
+  let top = center.y - NODE_SPACING * rows.saturating_sub(1) as f32 / 2.;
+  (0 .. rows)
+    .flat_map(|row| row_points(columns, Pos2::new(center.x, top + NODE_SPACING * row as f32)))
+    .collect()
+}
+
+fn path_edges(offset: usize, n: usize) -> Vec<(usize, usize)> {
+  (offset + 1 .. offset + n).map(|i| (i - 1, i)).collect()
+}
+
+fn cycle_edges(offset: usize, n: usize) -> Vec<(usize, usize)> {
+  let mut edges = path_edges(offset, n);
+  if n > 2 { edges.push((offset + n - 1, offset)) }
+  edges
+}
+
+fn grid_edges(rows: usize, columns: usize) -> Vec<(usize, usize)> {
+  let mut edges = Vec::new();
+  for row in 0 .. rows {
+    edges.extend(path_edges(row * columns, columns));
+    if row > 0 { edges.extend((0 .. columns).map(|column| ((row - 1) * columns + column, row * columns + column))) }
+  }
+  edges
+}
+
+// Outer cycle 0 .. n, spokes to the inner nodes n .. 2n, inner node i connects to i + k
+fn generalized_petersen(n: usize, k: usize, center: Pos2) -> (Vec<Pos2>, Vec<(usize, usize)>) {
+  let radius = (NODE_RADIUS * n as f32).max(200.);
+  let mut points = points_on_circle(n, center, radius);
+  points.extend(points_on_circle(n, center, radius / 2.));
+  let mut edges = cycle_edges(0, n);
+  edges.extend((0 .. n).map(|i| (i, n + i)));
+  edges.extend((0 .. n).map(|i| (n + i, n + (i + k) % n)));
+  (points, edges)
+}
+
+// Decodes a random Prüfer sequence, then lays the tree out in rows by depth from node 0
+fn random_tree(n: usize, seed: u64, center: Pos2) -> (Vec<Pos2>, Vec<(usize, usize)>) {
+  // splitmix64, we don't need anything fancier to pick a tree
+  let mut state = seed;
+  let mut next = |bound: usize| {
+    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (z ^ (z >> 31)) as usize % bound
+  };
+
+  let mut edges = Vec::with_capacity(n.saturating_sub(1));
+  if n == 2 { edges.push((0, 1)) }
+  if n > 2 {
+    let prufer = (0 .. n - 2).map(|_| next(n)).collect::<Vec<_>>();
+    let mut degree = vec![1; n];
+    for node in &prufer { degree[*node] += 1 }
+    for node in &prufer {
+      let leaf = (0 .. n).find(|leaf| degree[*leaf] == 1).unwrap();
+      edges.push((leaf, *node));
+      degree[leaf] -= 1;
+      degree[*node] -= 1;
+    }
+    let last = (0 .. n).filter(|node| degree[*node] == 1).collect::<Vec<_>>();
+    edges.push((last[0], last[1]));
+  }
+
+  let mut neighbors = vec![Vec::new(); n];
+  for (node1, node2) in &edges {
+    neighbors[*node1].push(*node2);
+    neighbors[*node2].push(*node1);
+  }
+  let mut rows = vec![vec![0]];
+  let mut seen = vec![false; n];
+  seen[0] = true;
+  while let Some(row) = rows.last() {
+    let next_row = row.iter()
+      .flat_map(|node| neighbors[*node].iter().copied())
+      .filter(|node| !std::mem::replace(&mut seen[*node], true))
+      .collect::<Vec<_>>()
+    ;
+    if next_row.is_empty() { break }
+    rows.push(next_row);
+  }
+
+  let mut points = vec![Pos2::ZERO; n];
+  let top = center.y - NODE_SPACING * (rows.len() - 1) as f32 / 2.;
+  for (depth, row) in rows.iter().enumerate() {
+    let row_center = Pos2::new(center.x, top + NODE_SPACING * depth as f32);
+    for (node, point) in row.iter().zip(row_points(row.len(), row_center)) { points[*node] = point }
+  }
+  (points, edges)
+}
@@ -0,0 +1,203 @@
+/// Serialize an undirected simple graph into graph6 format.
+///
+/// `adj[i]` contains the neighbors of vertex `i`.
+/// Assumes:
+/// - vertices are 0..n-1
+/// - no self-loops
+/// - undirected (i in adj[j] iff j in adj[i])
+///
+// Visualizer which allows graph6 imports
+// https://houseofgraphs.org/draw_graph
+pub fn to_graph6(adj: Vec< Vec<usize> >) -> String {
+  let n = adj.len();
+
+  // Build adjacency lookup for fast edge testing
+  let mut has_edge = vec![vec![false; n]; n];
+  for (u, neighbors) in adj.iter().enumerate() {
+    for &v in neighbors {
+      assert!(u != v, "Self-loops are not allowed");
+      has_edge[u][v] = true;
+      has_edge[v][u] = true;
+    }
+  }
+
+  // Collect upper-triangle bits in graph6 order
+  let mut bits = Vec::new();
+  for (j, column) in has_edge.iter().enumerate().skip(1) {
+    bits.extend_from_slice(&column[..j]);
+  }
+
+  encode(n, bits)
+}
+
+/// Serialize a directed graph into digraph6 format, the whole adjacency matrix row by row after a leading `&`.
+///
+/// `adj[i]` contains the out-neighbors of vertex `i`, with the same assumptions as [to_graph6] besides symmetry.
+pub fn to_digraph6(adj: Vec< Vec<usize> >) -> String {
+  let n = adj.len();
+
+  let mut bits = vec![false; n * n];
+  for (u, neighbors) in adj.iter().enumerate() {
+    for &v in neighbors {
+      assert!(u != v, "Self-loops are not allowed");
+      bits[u * n + v] = true;
+    }
+  }
+
+  format!("&{}", encode(n, bits))
+}
+
+// Number of vertices then the bits 6 at a time, shared by graph6 and digraph6
+fn encode(n: usize, mut bits: Vec<bool>) -> String {
+  // Encode number of vertices, a single byte up to 62, otherwise 126 followed by 18 bits,
+  // or 126 twice followed by 36 bits past 258047
+  let (prefix, digits) = match n {
+    0 ..= 62 => (0, 1),
+    63 ..= 258047 => (1, 3),
+    _ => (2, 6),
+  };
+  let mut output = "~".repeat(prefix);
+  for digit in (0 .. digits).rev() {
+    output.push((((n >> (6 * digit)) & 63) as u8 + 63) as char);
+  }
+
+  // Pad with zeros to multiple of 6
+  while !bits.len().is_multiple_of(6) {
+    bits.push(false);
+  }
+
+  // Encode bits in chunks of 6
+  for chunk in bits.chunks(6) {
+    let mut value = 0u8;
+    for &bit in chunk {
+      value = (value << 1) | bit as u8;
+    }
+    output.push((value + 63) as char);
+  }
+
+  output
+}
+
+/// Parse a graph6 string into an adjacency list, the inverse of [to_graph6].
+///
+/// Accepts an optional `>>graph6<<` header and surrounding whitespace,
+/// returns None if the string isn't valid graph6.
+pub fn from_graph6(input: &str) -> Option< Vec< Vec<usize> > > {
+  let input = input.trim();
+  let (n, bits) = decode(input.strip_prefix(">>graph6<<").unwrap_or(input))?;
+
+  // Upper-triangle bits in graph6 order, padding has to fit in the final byte
+  let edge_count = n.checked_mul(n.saturating_sub(1))? / 2;
+  if bits.len() < edge_count || bits.len() - edge_count >= 6 { return None }
+  let mut adj = vec![Vec::new(); n];
+  let mut bits = bits.into_iter();
+  for j in 1 .. n {
+    for i in 0 .. j {
+      if bits.next()? {
+        adj[i].push(j);
+        adj[j].push(i);
+      }
+    }
+  }
+
+  Some(adj)
+}
+
+/// Parse a digraph6 string into out-neighbor lists, the inverse of [to_digraph6].
+///
+/// Accepts an optional `>>digraph6<<` header and surrounding whitespace,
+/// returns None if the string isn't valid digraph6 or has a self-loop.
+pub fn from_digraph6(input: &str) -> Option< Vec< Vec<usize> > > {
+  let input = input.trim();
+  let (n, bits) = decode(input.strip_prefix(">>digraph6<<").unwrap_or(input).strip_prefix('&')?)?;
+
+  // The whole matrix row by row, padding has to fit in the final byte
+  let matrix = n.checked_mul(n)?;
+  if bits.len() < matrix || bits.len() - matrix >= 6 { return None }
+  let mut adj = vec![Vec::new(); n];
+  for (idx, bit) in bits.into_iter().take(matrix).enumerate() {
+    let (u, v) = (idx / n, idx % n);
+    if !bit { continue }
+    if u == v { return None }
+    adj[u].push(v);
+  }
+
+  Some(adj)
+}
+
+// Number of vertices and the bits after it, None on bytes outside of 63 ..= 126
+fn decode(input: &str) -> Option<(usize, Vec<bool>)> {
+  let mut bytes = input.bytes().map(|byte| {
+    if (63 ..= 126).contains(&byte) { Some(byte - 63) } else { None }
+  });
+
+  // Decode number of vertices, either a single byte, 126 followed by 18 bits or 126 twice followed by 36 bits
+  let n = match bytes.next()?? {
+    63 => {
+      let first = bytes.next()??;
+      let (mut n, digits) = if first == 63 { (0usize, 6) } else { (first as usize, 2) };
+      for _ in 0 .. digits { n = (n << 6) | bytes.next()?? as usize; }
+      n
+    }
+    small => small as usize,
+  };
+
+  let mut bits = Vec::new();
+  for value in bytes {
+    let value = value?;
+    for shift in (0 .. 6).rev() { bits.push((value >> shift) & 1 == 1); }
+  }
+  Some((n, bits))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{decode, encode, from_digraph6, from_graph6, to_digraph6, to_graph6};
+
+  fn sorted(mut adj: Vec< Vec<usize> >) -> Vec< Vec<usize> > {
+    for list in &mut adj { list.sort_unstable() }
+    adj
+  }
+
+  #[test]
+  fn headers_match_the_spec() {
+    // The examples from the graph6 format description
+    for (n, header) in [(30, vec![93]), (12345, vec![126, 66, 63, 120]), (460175067, vec![126, 126, 63, 90, 90, 90, 90, 90])] {
+      assert_eq!(encode(n, Vec::new()).into_bytes(), header);
+      assert_eq!(decode(&String::from_utf8(header).unwrap()).map(|(n, _)| n), Some(n));
+    }
+  }
+
+  #[test]
+  fn large_graph_round_trips() {
+    // A cycle with some chords, past the single byte header
+    for n in [63, 70, 300] {
+      let mut adj = vec![Vec::new(); n];
+      for node in 0 .. n {
+        for other in [(node + 1) % n, (node * 7 + 3) % n] {
+          if other != node && !adj[node].contains(&other) {
+            adj[node].push(other);
+            adj[other].push(node);
+          }
+        }
+      }
+      let adj = sorted(adj);
+      assert_eq!(from_graph6(&to_graph6(adj.clone())).map(sorted), Some(adj));
+    }
+  }
+
+  #[test]
+  fn large_digraph_round_trips() {
+    // A directed cycle with one-way chords
+    for n in [63, 70, 300] {
+      let adj: Vec< Vec<usize> > = (0 .. n).map(|node| {
+        let mut list = vec![(node + 1) % n];
+        let chord = (node * 7 + 3) % n;
+        if chord != node && !list.contains(&chord) { list.push(chord) }
+        list.sort_unstable();
+        list
+      }).collect();
+      assert_eq!(from_digraph6(&to_digraph6(adj.clone())).map(sorted), Some(adj));
+    }
+  }
+}
@@ -0,0 +1,191 @@
+//! Numbers describing the shape of a graph, ignoring positions and values.
+//! Independence and domination are exponential searches which give up after [SEARCH_BUDGET] steps,
+//! so a big graph can't freeze the UI. Automorphisms have their own, see [AutomorphismGroup].
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+use super::AutomorphismGroup;
+use super::layout::two_color;
+
+// Steps each search gets before it gives up
+const SEARCH_BUDGET: usize = 200_000;
+// Independence and domination keep sets of nodes in a u128
+const MAX_SET_NODES: usize = 128;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Invariants {
+  // Largest first
+  pub degrees: Vec<usize>,
+  pub components: usize,
+  // None when disconnected
+  pub diameter: Option<usize>,
+  // None without any cycles
+  pub girth: Option<usize>,
+  pub bipartite: bool,
+  // These are None when the search gave up, or the group size overflowed
+  pub independence: Option<usize>,
+  pub domination: Option<usize>,
+  pub automorphisms: Option<u128>,
+}
+impl Invariants {
+  /// Expects the neighbors of a contiguized graph, see [super::Graph::get_neighbors]
+  pub fn new(neighbors: &[Vec<usize>]) -> Self {
+    let mut degrees = neighbors.iter().map(Vec::len).collect::<Vec<_>>();
+    degrees.sort_unstable_by_key(|degree| Reverse(*degree));
+
+    let distances = (0 .. neighbors.len()).map(|root| distances(neighbors, root)).collect::<Vec<_>>();
+    let mut seen = vec![false; neighbors.len()];
+    let mut components = 0;
+    for (root, reached) in distances.iter().enumerate() {
+      if seen[root] { continue }
+      components += 1;
+      for (node, distance) in reached.iter().enumerate() {
+        if distance.is_some() { seen[node] = true }
+      }
+    }
+    let diameter = (components == 1).then(|| distances.iter().flatten().flatten().copied().max().unwrap_or(0));
+
+    Self {
+      degrees,
+      components,
+      diameter,
+      girth: girth(neighbors),
+      bipartite: two_color(neighbors).is_some(),
+      independence: independence(neighbors),
+      domination: domination(neighbors),
+      automorphisms: AutomorphismGroup::new(neighbors).and_then(|group| group.size),
+    }
+  }
+}
+
+// BFS distance to every node, None if it's unreachable
+fn distances(neighbors: &[Vec<usize>], root: usize) -> Vec< Option<usize> > {
+  let mut distances = vec![None; neighbors.len()];
+  distances[root] = Some(0);
+  let mut queue = VecDeque::from([(root, 0)]);
+  while let Some((node, distance)) = queue.pop_front() {
+    for neighbor in &neighbors[node] {
+      if distances[*neighbor].is_none() {
+        distances[*neighbor] = Some(distance + 1);
+        queue.push_back((*neighbor, distance + 1));
+      }
+    }
+  }
+  distances
+}
+
+// Any edge outside a BFS tree closes a cycle through the root, the shortest over every root is exact
+fn girth(neighbors: &[Vec<usize>]) -> Option<usize> {
+  let n = neighbors.len();
+  let mut girth = None;
+  for root in 0 .. n {
+    let mut distance = vec![usize::MAX; n];
+    let mut parent = vec![usize::MAX; n];
+    distance[root] = 0;
+    let mut queue = VecDeque::from([root]);
+    while let Some(node) = queue.pop_front() {
+      for neighbor in &neighbors[node] {
+        if distance[*neighbor] == usize::MAX {
+          distance[*neighbor] = distance[node] + 1;
+          parent[*neighbor] = node;
+          queue.push_back(*neighbor);
+        } else if parent[node] != *neighbor {
+          let cycle = distance[node] + distance[*neighbor] + 1;
+          girth = Some(girth.map_or(cycle, |girth: usize| girth.min(cycle)));
+        }
+      }
+    }
+  }
+  girth
+}
+
+fn independence(neighbors: &[Vec<usize>]) -> Option<usize> {
+  let masks = neighbor_masks(neighbors)?;
+  let mut best = 0;
+  let mut budget = SEARCH_BUDGET;
+  largest_independent(&masks, full_set(neighbors.len()), 0, &mut best, &mut budget)?;
+  Some(best)
+}
+
+// Branches on the candidate with the most candidate neighbors, taking it or leaving it out
+fn largest_independent(masks: &[u128], candidates: u128, size: usize, best: &mut usize, budget: &mut usize) -> Option<()> {
+  *budget = budget.checked_sub(1)?;
+  if size + candidates.count_ones() as usize <= *best { return Some(()) }
+
+  let degree = |node: usize| (masks[node] & candidates).count_ones();
+  let Some(leaf) = set_nodes(candidates).min_by_key(|node| degree(*node)) else {
+    *best = size;
+    return Some(())
+  };
+  let take = |node: usize| candidates & !masks[node] & !(1 << node);
+  // Some largest set always has a node with at most one neighbor in it, so there's nothing to branch on
+  if degree(leaf) <= 1 { return largest_independent(masks, take(leaf), size + 1, best, budget) }
+
+  let node = set_nodes(candidates).max_by_key(|node| degree(*node)).unwrap();
+  largest_independent(masks, take(node), size + 1, best, budget)?;
+  largest_independent(masks, candidates & !(1 << node), size, best, budget)
+}
+
+fn domination(neighbors: &[Vec<usize>]) -> Option<usize> {
+  let closed = neighbor_masks(neighbors)?.into_iter()
+    .enumerate()
+    .map(|(node, mask)| mask | 1 << node)
+    .collect::<Vec<_>>()
+  ;
+  let all = full_set(neighbors.len());
+
+  // Greedily taking whatever dominates the most gives a set to beat
+  let mut dominated = 0;
+  let mut best = 0;
+  while dominated != all {
+    let node = (0 .. closed.len()).max_by_key(|node| (closed[*node] & !dominated).count_ones()).unwrap();
+    dominated |= closed[node];
+    best += 1;
+  }
+
+  let reach = closed.iter().map(|mask| mask.count_ones() as usize).max().unwrap_or(1);
+  let mut budget = SEARCH_BUDGET;
+  smallest_dominating(&closed, all, 0, 0, reach, &mut best, &mut budget)?;
+  Some(best)
+}
+
+// Some node in the closed neighborhood of every undominated node has to be taken,
+// so branch over the undominated node with the fewest options
+fn smallest_dominating(
+  closed: &[u128], all: u128, dominated: u128, size: usize, reach: usize, best: &mut usize, budget: &mut usize
+) -> Option<()> {
+  *budget = budget.checked_sub(1)?;
+  let undominated = all & !dominated;
+  if undominated == 0 {
+    *best = (*best).min(size);
+    return Some(())
+  }
+  // Every node taken dominates at most reach more
+  if size + (undominated.count_ones() as usize).div_ceil(reach) >= *best { return Some(()) }
+
+  let node = set_nodes(undominated).min_by_key(|node| closed[*node].count_ones()).unwrap();
+  let mut choices = set_nodes(closed[node]).collect::<Vec<_>>();
+  choices.sort_by_key(|choice| Reverse((closed[*choice] & undominated).count_ones()));
+  for choice in choices {
+    smallest_dominating(closed, all, dominated | closed[choice], size + 1, reach, best, budget)?;
+  }
+  Some(())
+}
+
+fn neighbor_masks(neighbors: &[Vec<usize>]) -> Option< Vec<u128> > {
+  if neighbors.len() > MAX_SET_NODES { return None }
+  Some(neighbors.iter().map(|neighbors| neighbors.iter().fold(0, |mask, neighbor| mask | 1 << neighbor)).collect())
+}
+
+fn full_set(n: usize) -> u128 {
+  if n == MAX_SET_NODES { u128::MAX } else { (1 << n) - 1 }
+}
+
+fn set_nodes(mut set: u128) -> impl Iterator<Item = usize> {
+  std::iter::from_fn(move || (set != 0).then(|| {
+    let node = set.trailing_zeros() as usize;
+    set &= set - 1;
+    node
+  }))
+}
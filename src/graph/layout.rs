@@ -0,0 +1,291 @@
+//! Automatic layouts which rewrite [super::Node::position], each fitted into the space it's given
+
+use emath::{Pos2, Rect, Vec2};
+
+use super::ops::compact;
+use super::{Graph, NODE_RADIUS, NODE_SPACING, points_on_circle, row_points, underlying};
+
+// Iterations and starting temperature for a full force directed layout
+const FORCE_ITERATIONS: usize = 300;
+const FORCE_TEMPERATURE: f32 = 100.;
+// How hard the force layout pulls towards the middle, relative to an edge
+const GRAVITY: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+  ForceDirected,
+  Circular,
+  // Two columns, one per side
+  Bipartite,
+  // Rows by distance from the first node of each component
+  Layered,
+  Spectral,
+}
+impl std::fmt::Display for Layout {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let str = match self {
+      Self::ForceDirected => "Force Directed",
+      Self::Circular => "Circular",
+      Self::Bipartite => "Bipartite",
+      Self::Layered => "Layered",
+      Self::Spectral => "Spectral",
+    };
+    write!(f, "{}", str)
+  }
+}
+impl Layout {
+  pub const ALL: [Self; 5] = [Self::ForceDirected, Self::Circular, Self::Bipartite, Self::Layered, Self::Spectral];
+
+  /// False if the layout doesn't apply, which only happens for bipartite on a graph that isn't
+  pub fn apply(self, graph: &mut Graph, space: Rect) -> bool {
+    let (positions, neighbors, slots) = compact_underlying(graph);
+    let points = match self {
+      Self::ForceDirected => {
+        let mut points = positions;
+        for iteration in 0 .. FORCE_ITERATIONS {
+          let temperature = FORCE_TEMPERATURE * (1. - iteration as f32 / FORCE_ITERATIONS as f32);
+          force_step(&mut points, &neighbors, temperature);
+        }
+        points
+      }
+      Self::Circular => points_on_circle(neighbors.len(), Pos2::ZERO, NODE_SPACING),
+      Self::Bipartite => {
+        let Some(sides) = two_color(&neighbors) else { return false };
+        let mut points = vec![Pos2::ZERO; neighbors.len()];
+        for side in [false, true] {
+          let column = (0 .. neighbors.len()).filter(|node| sides[*node] == side).collect::<Vec<_>>();
+          // row_points lays out horizontally, so flip it into a column
+          let x = if side { NODE_SPACING * 2. } else { 0. };
+          for (node, point) in column.iter().zip(row_points(column.len(), Pos2::ZERO)) {
+            points[*node] = Pos2::new(x, point.x);
+          }
+        }
+        points
+      }
+      Self::Layered => {
+        let mut points = vec![Pos2::ZERO; neighbors.len()];
+        for (depth, layer) in layers(&neighbors).iter().enumerate() {
+          let row = row_points(layer.len(), Pos2::new(0., NODE_SPACING * depth as f32));
+          for (node, point) in layer.iter().zip(row) { points[*node] = point }
+        }
+        points
+      }
+      Self::Spectral => spectral(&neighbors),
+    };
+
+    write_back(graph, &slots, &points);
+    fit(graph, space);
+    true
+  }
+}
+
+/// One Fruchterman-Reingold step on the graph, for animating a layout a frame at a time.
+/// Nodes move at most `temperature`, returns how far the furthest one moved.
+pub fn force_step_graph(graph: &mut Graph, temperature: f32) -> f32 {
+  let (mut points, neighbors, slots) = compact_underlying(graph);
+  let moved = force_step(&mut points, &neighbors, temperature);
+  write_back(graph, &slots, &points);
+  moved
+}
+
+/// Scales and moves the graph to fill `space`, leaving room for the nodes at the edges
+pub fn fit(graph: &mut Graph, space: Rect) {
+  let positions = graph.nodes.iter().map(|(_, node)| node.position).collect::<Vec<_>>();
+  if positions.is_empty() { return }
+  let bounds = Rect::from_points(&positions);
+  let target = space.shrink(NODE_RADIUS * 1.5);
+
+  // A line of nodes has no size in one direction, so only the other one matters
+  let scale_x = if bounds.width() > f32::EPSILON { target.width() / bounds.width() } else { f32::INFINITY };
+  let scale_y = if bounds.height() > f32::EPSILON { target.height() / bounds.height() } else { f32::INFINITY };
+  let scale = scale_x.min(scale_y);
+  let scale = if scale.is_finite() { scale } else { 1. };
+
+  for (_, node) in graph.nodes.iter_mut() {
+    node.position = target.center() + (node.position - bounds.center()) * scale;
+  }
+}
+
+// Returns how far the furthest node moved
+fn force_step(points: &mut [Pos2], neighbors: &[Vec<usize>], temperature: f32) -> f32 {
+  // Ideal edge length
+  let k = NODE_SPACING;
+  let mut displacement = vec![Vec2::ZERO; points.len()];
+  for i in 0 .. points.len() {
+    for j in i + 1 .. points.len() {
+      let mut delta = points[i] - points[j];
+      // Nodes on top of each other get pushed apart in a direction picked by index
+      if delta.length_sq() < 0.01 { delta = Vec2::angled(i as f32 + j as f32 * 0.7) * 0.1 }
+      let push = delta.normalized() * (k * k / delta.length());
+      displacement[i] += push;
+      displacement[j] -= push;
+    }
+    for j in &neighbors[i] {
+      let delta = points[i] - points[*j];
+      displacement[i] -= delta.normalized() * (delta.length_sq() / k);
+    }
+  }
+  // A little gravity so nodes without neighbors don't drift off forever
+  let center = points.iter().fold(Vec2::ZERO, |sum, point| sum + point.to_vec2()) / points.len().max(1) as f32;
+  for (point, displacement) in points.iter().zip(displacement.iter_mut()) {
+    let delta = *point - center.to_pos2();
+    *displacement -= delta.normalized() * (delta.length_sq() / k) * GRAVITY;
+  }
+
+  let mut moved = 0f32;
+  for (point, displacement) in points.iter_mut().zip(displacement) {
+    let step = displacement.length().min(temperature);
+    if step > 0. { *point += displacement.normalized() * step }
+    moved = moved.max(step);
+  }
+  moved
+}
+
+// Layouts only care what's adjacent, so directed graphs get laid out as their underlying graph
+fn compact_underlying(graph: &Graph) -> (Vec<Pos2>, Vec< Vec<usize> >, Vec< Option<usize> >) {
+  let (positions, neighbors, slots) = compact(graph);
+  (positions, underlying(&neighbors), slots)
+}
+
+fn write_back(graph: &mut Graph, slots: &[Option<usize>], points: &[Pos2]) {
+  for (slot, node) in slots.iter().enumerate() {
+    if let Some(node) = node && let Some(graph_node) = graph.nodes.get_mut(slot) {
+      graph_node.position = points[*node];
+    }
+  }
+}
+
+// BFS layers, every component starts at its first node
+fn layers(neighbors: &[Vec<usize>]) -> Vec< Vec<usize> > {
+  let mut depth = vec![None; neighbors.len()];
+  let mut layers: Vec< Vec<usize> > = Vec::new();
+  for root in 0 .. neighbors.len() {
+    if depth[root].is_some() { continue }
+    depth[root] = Some(0);
+    let mut frontier = vec![root];
+    let mut cur_depth = 0;
+    while !frontier.is_empty() {
+      if layers.len() <= cur_depth { layers.push(Vec::new()) }
+      layers[cur_depth].extend(&frontier);
+      let mut next = Vec::new();
+      for node in frontier {
+        for neighbor in &neighbors[node] {
+          if depth[*neighbor].is_none() {
+            depth[*neighbor] = Some(cur_depth + 1);
+            next.push(*neighbor);
+          }
+        }
+      }
+      frontier = next;
+      cur_depth += 1;
+    }
+  }
+  layers
+}
+
+// Which side each node is on, None if there's an odd cycle
+pub(super) fn two_color(neighbors: &[Vec<usize>]) -> Option< Vec<bool> > {
+  let mut side = vec![None; neighbors.len()];
+  for root in 0 .. neighbors.len() {
+    if side[root].is_some() { continue }
+    side[root] = Some(false);
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+      let node_side = side[node]?;
+      for neighbor in &neighbors[node] {
+        match side[*neighbor] {
+          None => { side[*neighbor] = Some(!node_side); stack.push(*neighbor) }
+          Some(neighbor_side) if neighbor_side == node_side => return None,
+          Some(_) => (),
+        }
+      }
+    }
+  }
+  side.into_iter().collect()
+}
+
+// Positions from the eigenvectors of the two smallest nonzero Laplacian eigenvalues
+fn spectral(neighbors: &[Vec<usize>]) -> Vec<Pos2> {
+  let n = neighbors.len();
+  if n < 3 { return points_on_circle(n, Pos2::ZERO, NODE_SPACING) }
+
+  let mut laplacian = vec![vec![0f64; n]; n];
+  for (node, node_neighbors) in neighbors.iter().enumerate() {
+    laplacian[node][node] = node_neighbors.len() as f64;
+    for neighbor in node_neighbors { laplacian[node][*neighbor] = -1. }
+  }
+  let (values, vectors) = jacobi_eigen(laplacian);
+  let mut order = (0 .. n).collect::<Vec<_>>();
+  order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+
+  // Skip the constant eigenvector of every component
+  let nonzero = order.into_iter().filter(|idx| values[*idx] > 1e-6).collect::<Vec<_>>();
+  if nonzero.len() < 2 { return points_on_circle(n, Pos2::ZERO, NODE_SPACING) }
+  let (x, y) = (nonzero[0], nonzero[1]);
+  let mut points = (0 .. n).map(|node| Pos2::new(vectors[node][x] as f32, vectors[node][y] as f32)).collect::<Vec<_>>();
+
+  // Scale edges to about NODE_SPACING, then split up nodes that landed on the same spot,
+  // which repeated eigenvalues love to do
+  let edges = neighbors.iter().enumerate().flat_map(|(node, node_neighbors)| node_neighbors.iter().map(move |neighbor| (node, *neighbor)));
+  let (total, count) = edges.fold((0., 0), |(total, count), (node1, node2)| (total + points[node1].distance(points[node2]), count + 1));
+  if total > 0. {
+    let scale = NODE_SPACING * count as f32 / total;
+    for point in points.iter_mut() { *point = (point.to_vec2() * scale).to_pos2() }
+  }
+  separate(&mut points, NODE_RADIUS * 2.);
+  points
+}
+
+// Pushes apart any nodes closer than `distance`, without touching the rest
+fn separate(points: &mut [Pos2], distance: f32) {
+  for _ in 0 .. 100 {
+    let mut overlapping = false;
+    for i in 0 .. points.len() {
+      for j in i + 1 .. points.len() {
+        let mut delta = points[i] - points[j];
+        if delta.length() >= distance { continue }
+        overlapping = true;
+        if delta.length_sq() < 0.01 { delta = Vec2::angled(i as f32 + j as f32 * 0.7) }
+        let push = delta.normalized() * (distance - delta.length()) / 2.;
+        points[i] += push;
+        points[j] -= push;
+      }
+    }
+    if !overlapping { break }
+  }
+}
+
+// Eigenvalues and eigenvectors (as columns) of a symmetric matrix by cyclic Jacobi rotations
+fn jacobi_eigen(mut matrix: Vec< Vec<f64> >) -> (Vec<f64>, Vec< Vec<f64> >) {
+  let n = matrix.len();
+  let mut vectors = (0 .. n).map(|i| (0 .. n).map(|j| if i == j { 1. } else { 0. }).collect::<Vec<f64>>()).collect::<Vec<_>>();
+  for _ in 0 .. 100 {
+    let off_diagonal: f64 = (0 .. n).flat_map(|i| (0 .. n).filter(move |j| *j != i).map(move |j| (i, j)))
+      .map(|(i, j)| matrix[i][j] * matrix[i][j])
+      .sum()
+    ;
+    if off_diagonal < 1e-18 { break }
+    for p in 0 .. n {
+      for q in p + 1 .. n {
+        if matrix[p][q].abs() < 1e-12 { continue }
+        let theta = (matrix[q][q] - matrix[p][p]) / (2. * matrix[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.).sqrt());
+        let c = 1. / (t * t + 1.).sqrt();
+        let s = t * c;
+        // Rotate columns p and q, then rows p and q, of the matrix and the columns of the vectors
+        for row in matrix.iter_mut().chain(vectors.iter_mut()) {
+          let (kp, kq) = (row[p], row[q]);
+          row[p] = c * kp - s * kq;
+          row[q] = s * kp + c * kq;
+        }
+        let (upper, lower) = matrix.split_at_mut(q);
+        for (pk, qk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+          let (old_pk, old_qk) = (*pk, *qk);
+          *pk = c * old_pk - s * old_qk;
+          *qk = s * old_pk + c * old_qk;
+        }
+      }
+    }
+  }
+  ((0 .. n).map(|i| matrix[i][i]).collect(), vectors)
+}
@@ -0,0 +1,254 @@
+//! Operations building a new graph out of existing ones.
+//! Results are contiguous with every value at 0, and nodes of the inputs are
+//! numbered in the order [lilypads::Pond::iter] visits them.
+
+use ahash::AHashSet;
+use emath::{Pos2, Rect, Vec2};
+
+use super::{Graph, NODE_RADIUS, NODE_SPACING, points_on_circle};
+
+type Edges = Vec<(usize, usize)>;
+
+/// Same nodes, with an edge exactly where `graph` has none
+pub fn complement(graph: &Graph) -> Graph {
+  let (positions, neighbors, _) = compact(graph);
+  let n = positions.len();
+  let edges = (0 .. n)
+    .flat_map(|i| (i + 1 .. n).map(move |j| (i, j)))
+    .filter(|(i, j)| !neighbors[*i].contains(j))
+    .collect::<Edges>()
+  ;
+  assemble(positions, edges)
+}
+
+/// A node for every edge, connected when the edges share an endpoint
+pub fn line_graph(graph: &Graph) -> Graph {
+  let (positions, _, _) = compact(graph);
+  let lines = edges(graph);
+  let mut edges = Edges::new();
+  for (i, (a1, a2)) in lines.iter().enumerate() {
+    for (j, (b1, b2)) in lines.iter().enumerate().skip(i + 1) {
+      if a1 == b1 || a1 == b2 || a2 == b1 || a2 == b2 { edges.push((i, j)) }
+    }
+  }
+
+  // Sit on the middle of the edge, unless that piles nodes on top of each other
+  let midpoints = lines.iter()
+    .map(|(node1, node2)| positions[*node1].lerp(positions[*node2], 0.5))
+    .collect::<Vec<_>>()
+  ;
+  let crowded = midpoints.iter().enumerate().any(|(i, a)| {
+    midpoints[i + 1 ..].iter().any(|b| a.distance(*b) < NODE_RADIUS * 2.)
+  });
+  let points = if crowded {
+    let radius = (NODE_RADIUS * lines.len() as f32).max(200.);
+    points_on_circle(lines.len(), bounds(&positions).center(), radius)
+  } else { midpoints };
+  assemble(points, edges)
+}
+
+/// (u, v) and (u', v') are neighbors when one side is equal and the other side neighbors
+pub fn cartesian_product(a: &Graph, b: &Graph) -> Graph {
+  product(a, b, |same_a, adjacent_a, same_b, adjacent_b| (same_a && adjacent_b) || (adjacent_a && same_b))
+}
+
+/// (u, v) and (u', v') are neighbors when both sides neighbor
+pub fn tensor_product(a: &Graph, b: &Graph) -> Graph {
+  product(a, b, |_, adjacent_a, _, adjacent_b| adjacent_a && adjacent_b)
+}
+
+/// Union of the cartesian and tensor products
+pub fn strong_product(a: &Graph, b: &Graph) -> Graph {
+  product(a, b, |same_a, adjacent_a, same_b, adjacent_b| {
+    (same_a || adjacent_a) && (same_b || adjacent_b) && !(same_a && same_b)
+  })
+}
+
+/// `a` and `b` side by side, `a` first
+pub fn disjoint_union(a: &Graph, b: &Graph) -> Graph {
+  let (points, edges, _) = side_by_side(a, b);
+  assemble(points, edges)
+}
+
+/// Disjoint union with every node of `a` connected to every node of `b`
+pub fn join(a: &Graph, b: &Graph) -> Graph {
+  let (points, mut edges, a_len) = side_by_side(a, b);
+  let n = points.len();
+  edges.extend((0 .. a_len).flat_map(|i| (a_len .. n).map(move |j| (i, j))));
+  assemble(points, edges)
+}
+
+/// Puts a new node in the middle of the edge, None if there's no such edge
+pub fn subdivide(graph: &Graph, node1: usize, node2: usize) -> Option<Graph> {
+  if !graph.has_connection(node1, node2) { return None }
+  let (mut positions, _, slots) = compact(graph);
+  let (node1, node2) = (slots.get(node1).copied()??, slots.get(node2).copied()??);
+  let middle = positions.len();
+  positions.push(positions[node1].lerp(positions[node2], 0.5));
+  let mut edges = compact_edges(graph, &slots);
+  edges.retain(|edge| *edge != (node1.min(node2), node1.max(node2)));
+  edges.extend([(node1, middle), (middle, node2)]);
+  Some(assemble(positions, edges))
+}
+
+/// Merges the two nodes into one halfway between them which keeps both their neighbors,
+/// None if they're the same node or either is missing
+pub fn contract(graph: &Graph, node1: usize, node2: usize) -> Option<Graph> {
+  if node1 == node2 { return None }
+  let (positions, _, slots) = compact(graph);
+  let (kept, removed) = (slots.get(node1.min(node2)).copied()??, slots.get(node1.max(node2)).copied()??);
+  // Everything after the removed node shifts down by one
+  let renumber = |node: usize| {
+    if node == removed { kept } else if node > removed { node - 1 } else { node }
+  };
+
+  let mut points = positions.clone();
+  points[kept] = positions[kept].lerp(positions[removed], 0.5);
+  points.remove(removed);
+  let edges = compact_edges(graph, &slots).into_iter()
+    .map(|(node1, node2)| (renumber(node1), renumber(node2)))
+    .filter(|(node1, node2)| node1 != node2)
+    .collect()
+  ;
+  Some(assemble(points, edges))
+}
+
+// Node (u, v) is u * |b| + v, `adjacent` gets (u == u', u ~ u', v == v', v ~ v')
+fn product(a: &Graph, b: &Graph, adjacent: impl Fn(bool, bool, bool, bool) -> bool) -> Graph {
+  let (a_positions, a_neighbors, _) = compact(a);
+  let (b_positions, b_neighbors, _) = compact(b);
+  let (a_len, b_len) = (a_positions.len(), b_positions.len());
+
+  // Spread a out until a copy of b fits around each of its nodes
+  let a_center = bounds(&a_positions).center();
+  let b_bounds = bounds(&b_positions);
+  let closest = a_positions.iter().enumerate()
+    .flat_map(|(i, p)| a_positions[i + 1 ..].iter().map(move |q| p.distance(*q)))
+    .fold(f32::INFINITY, f32::min)
+  ;
+  let spread = if closest.is_finite() && closest > 0. {
+    (b_bounds.size().max_elem() + NODE_SPACING) / closest
+  } else { 1. };
+
+  let mut points = Vec::with_capacity(a_len * b_len);
+  for a_position in &a_positions {
+    for b_position in &b_positions {
+      points.push(a_center + (*a_position - a_center) * spread + (*b_position - b_bounds.center()));
+    }
+  }
+
+  let mut edges = Edges::new();
+  for (u1, u1_neighbors) in a_neighbors.iter().enumerate() {
+    for (v1, v1_neighbors) in b_neighbors.iter().enumerate() {
+      for u2 in u1 .. a_len {
+        for v2 in 0 .. b_len {
+          let (node1, node2) = (u1 * b_len + v1, u2 * b_len + v2);
+          if node2 <= node1 { continue }
+          let (same_a, same_b) = (u1 == u2, v1 == v2);
+          let (adjacent_a, adjacent_b) = (u1_neighbors.contains(&u2), v1_neighbors.contains(&v2));
+          if adjacent(same_a, adjacent_a, same_b, adjacent_b) { edges.push((node1, node2)) }
+        }
+      }
+    }
+  }
+  assemble(points, edges)
+}
+
+// Both graphs' points and edges with b placed to the right of a, and how many nodes a has
+fn side_by_side(a: &Graph, b: &Graph) -> (Vec<Pos2>, Edges, usize) {
+  let (a_positions, _, a_slots) = compact(a);
+  let (b_positions, _, b_slots) = compact(b);
+  let (a_bounds, b_bounds) = (bounds(&a_positions), bounds(&b_positions));
+  let width = a_bounds.width() + NODE_SPACING + b_bounds.width();
+
+  // Keep the combined graph centered where a was
+  let a_shift = Vec2::new(-(width - a_bounds.width()) / 2., 0.);
+  let b_left = a_bounds.center().x - width / 2. + a_bounds.width() + NODE_SPACING;
+  let b_shift = Vec2::new(b_left - b_bounds.min.x, a_bounds.center().y - b_bounds.center().y);
+
+  let a_len = a_positions.len();
+  let mut points = a_positions.into_iter().map(|point| point + a_shift).collect::<Vec<_>>();
+  points.extend(b_positions.into_iter().map(|point| point + b_shift));
+  let mut edges = compact_edges(a, &a_slots);
+  edges.extend(compact_edges(b, &b_slots).into_iter().map(|(node1, node2)| (node1 + a_len, node2 + a_len)));
+  (points, edges, a_len)
+}
+
+// Positions and neighbors with nodes renumbered 0 .. n, plus where each slot of the pond ended up
+pub(super) fn compact(graph: &Graph) -> (Vec<Pos2>, Vec< Vec<usize> >, Vec< Option<usize> >) {
+  let mut slots = vec![None; graph.nodes.len()];
+  let mut positions = Vec::new();
+  for (slot, node) in graph.nodes.iter() {
+    slots[slot] = Some(positions.len());
+    positions.push(node.position);
+  }
+  let neighbors = graph.nodes.iter()
+    .map(|(_, node)| node.neighbors.iter().filter_map(|neighbor| slots[*neighbor]).collect())
+    .collect()
+  ;
+  (positions, neighbors, slots)
+}
+
+// Every edge once as (smaller, bigger) in compact numbering
+fn compact_edges(graph: &Graph, slots: &[Option<usize>]) -> Edges {
+  let mut edges = AHashSet::new();
+  for (slot, node) in graph.nodes.iter() {
+    for neighbor in &node.neighbors {
+      if let (Some(node1), Some(node2)) = (slots[slot], slots[*neighbor]) {
+        edges.insert((node1.min(node2), node1.max(node2)));
+      }
+    }
+  }
+  let mut edges = edges.into_iter().collect::<Edges>();
+  edges.sort_unstable();
+  edges
+}
+
+fn edges(graph: &Graph) -> Edges {
+  let (_, _, slots) = compact(graph);
+  compact_edges(graph, &slots)
+}
+
+fn bounds(points: &[Pos2]) -> Rect {
+  if points.is_empty() { return Rect::from_min_max(Pos2::ZERO, Pos2::ZERO) }
+  Rect::from_points(points)
+}
+
+fn assemble(points: Vec<Pos2>, edges: Edges) -> Graph {
+  let mut graph = Graph::new();
+  for point in points { graph.add_node(point); }
+  for (node1, node2) in edges { graph.attempt_unique_connection(node1, node2); }
+  graph
+}
+
+#[cfg(test)]
+mod tests {
+  use emath::{Pos2, Rect, Vec2};
+
+  use super::super::{GraphType, from_graph6, to_graph6};
+  use super::{cartesian_product, join, line_graph, strong_product};
+
+  fn sorted(mut neighbors: Vec< Vec<usize> >) -> Vec< Vec<usize> > {
+    for list in &mut neighbors { list.sort_unstable() }
+    neighbors
+  }
+
+  #[test]
+  fn large_results_round_trip_through_graph6() {
+    let space = Rect::from_min_size(Pos2::ZERO, Vec2::splat(1000.));
+    let build = |spec: &str| GraphType::from_spec(spec).unwrap().build(space);
+    let results = [
+      cartesian_product(&build("cycle:8"), &build("cycle:9")),
+      strong_product(&build("path:8"), &build("star:7")),
+      join(&build("complete:40"), &build("cycle:30")),
+      line_graph(&build("complete:12")),
+    ];
+    for result in results {
+      // What Save copies and Import builds
+      let neighbors = sorted(result.get_neighbors());
+      assert!(neighbors.len() > 62);
+      let imported = from_graph6(&to_graph6(neighbors.clone())).unwrap();
+      assert_eq!(sorted(GraphType::Imported(imported).build(space).get_neighbors()), neighbors);
+    }
+  }
+}
@@ -0,0 +1,80 @@
+use emath::Pos2;
+
+use super::Graph;
+
+const HEADER: &str = "graph-explorer project 1";
+
+/// Serialize a graph with its node positions and values, plus max, into the plain-text project format.
+///
+/// ```text
+/// graph-explorer project 1
+/// max 2
+/// [directed]
+/// node <x> <y> <value> [max]
+/// edge <node> <node>
+/// ```
+/// Directed graphs have a `directed` line before any edge, then each edge is an arc from the first node to the second.
+/// A node's max is only written when it has its own, see [super::Node::max].
+/// Nodes are numbered in the order they're listed, blank lines and lines starting with # are ignored.
+/// Assumes the graph has already been contiguized by [Graph::contiguize_and_trim].
+pub fn to_project(graph: &Graph, max: u8) -> String {
+  let mut output = format!("{HEADER}\nmax {max}\n");
+  if graph.directed { output.push_str("directed\n") }
+  for (_, node) in graph.nodes.iter() {
+    output.push_str(&format!("node {} {} {}", node.position.x, node.position.y, node.value));
+    if let Some(max) = node.max { output.push_str(&format!(" {max}")) }
+    output.push('\n');
+  }
+  for (idx, node) in graph.nodes.iter() {
+    for neighbor in &node.neighbors {
+      if graph.directed || *neighbor > idx { output.push_str(&format!("edge {idx} {neighbor}\n")) }
+    }
+  }
+  output
+}
+
+/// Parse the output of [to_project] back into the graph and max, None if anything is malformed
+pub fn from_project(input: &str) -> Option<(Graph, u8)> {
+  let mut lines = input.lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+  ;
+  if lines.next()? != HEADER { return None }
+
+  let mut graph = Graph::new();
+  let mut max = None;
+  for line in lines {
+    let mut words = line.split_whitespace();
+    match words.next()? {
+      "directed" => {
+        // Edges before it would have gone both ways
+        if graph.nodes.iter().any(|(_, node)| !node.neighbors.is_empty()) { return None }
+        graph.directed = true;
+      }
+      "max" => max = Some(words.next()?.parse::<u8>().ok().filter(|max| *max <= 9)?),
+      "node" => {
+        let x = words.next()?.parse().ok()?;
+        let y = words.next()?.parse().ok()?;
+        let value: u8 = words.next()?.parse().ok()?;
+        let own_max = match words.next() {
+          Some(own_max) => Some(own_max.parse::<u8>().ok().filter(|own_max| *own_max <= 9)?),
+          None => None,
+        };
+        if value > own_max.or(max)? { return None }
+        let node = graph.add_node(Pos2::new(x, y));
+        let node = graph.nodes.get_mut(node)?;
+        node.value = value;
+        node.max = own_max;
+      }
+      "edge" => {
+        let node1 = words.next()?.parse().ok()?;
+        let node2 = words.next()?.parse().ok()?;
+        if node1 == node2 || !graph.attempt_unique_connection(node1, node2) { return None }
+      }
+      _ => return None,
+    }
+    if words.next().is_some() { return None }
+  }
+
+  Some((graph, max?))
+}
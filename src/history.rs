@@ -0,0 +1,103 @@
+use eframe::egui::Pos2;
+use graph_explorer::graph::{Graph, Node};
+use graph_explorer::state::AnyState;
+
+// Oldest edits get dropped past this
+const HISTORY_LEN: usize = 200;
+
+/// Something the user did which can be undone
+#[derive(Debug)]
+pub enum Edit {
+  AddNode(usize, Pos2),
+  // (node, removed, in-neighbors)
+  RemoveNode(usize, Node, Vec<usize>),
+  Connect(usize, usize),
+  Disconnect(usize, usize),
+  // (node, from, to)
+  MoveNode(usize, Pos2, Pos2),
+  // (from, to)
+  SetState(AnyState, AnyState),
+  // Whole graph swaps from graph operations, (name, (before, after))
+  Replace(&'static str, Box<(Graph, Graph)>),
+}
+impl std::fmt::Display for Edit {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::AddNode(node, _) => write!(f, "Add Node {node}"),
+      Self::RemoveNode(node, _, _) => write!(f, "Remove Node {node}"),
+      Self::Connect(node1, node2) => write!(f, "Connect {node1} and {node2}"),
+      Self::Disconnect(node1, node2) => write!(f, "Disconnect {node1} and {node2}"),
+      Self::MoveNode(node, _, _) => write!(f, "Move Node {node}"),
+      Self::SetState(_, _) => write!(f, "Change State"),
+      Self::Replace(name, _) => write!(f, "{name}"),
+    }
+  }
+}
+impl Edit {
+  /// Graph edits only make sense without a state space, state edits only with one
+  pub fn is_graph_edit(&self) -> bool { !matches!(self, Self::SetState(..)) }
+
+  fn apply(&self, graph: &mut Graph, desired_state: &mut AnyState, undo: bool) {
+    match (self, undo) {
+      (Self::AddNode(node, _), true) | (Self::RemoveNode(node, _, _), false) => {
+        if graph.nodes.is_occupied(*node) { graph.remove(*node); }
+      }
+      (Self::AddNode(node, position), false) => graph.insert_at(*node, Node::new(*position), &[]),
+      (Self::RemoveNode(node, removed, in_neighbors), true) => graph.insert_at(*node, removed.clone(), in_neighbors),
+      (Self::Connect(node1, node2), false) | (Self::Disconnect(node1, node2), true) => {
+        graph.attempt_unique_connection(*node1, *node2);
+      }
+      (Self::Connect(node1, node2), true) | (Self::Disconnect(node1, node2), false) => {
+        graph.remove_connection(*node1, *node2);
+      }
+      (Self::MoveNode(node, from, to), undo) => if let Some(node) = graph.nodes.get_mut(*node) {
+        node.position = if undo { *from } else { *to };
+      }
+      (Self::SetState(from, to), undo) => *desired_state = if undo { *from } else { *to },
+      (Self::Replace(_, graphs), undo) => *graph = if undo { graphs.0.clone() } else { graphs.1.clone() },
+    }
+  }
+}
+
+/// Edits before the cursor have been done, edits after it have been undone
+#[derive(Debug, Default)]
+pub struct History {
+  edits: Vec<Edit>,
+  cursor: usize,
+}
+impl History {
+  /// Throws away anything that was undone
+  pub fn record(&mut self, edit: Edit) {
+    self.edits.truncate(self.cursor);
+    self.edits.push(edit);
+    if self.edits.len() > HISTORY_LEN { self.edits.remove(0); }
+    self.cursor = self.edits.len();
+  }
+
+  pub fn next_undo(&self) -> Option<&Edit> { self.edits.get(self.cursor.checked_sub(1)?) }
+  pub fn next_redo(&self) -> Option<&Edit> { self.edits.get(self.cursor) }
+
+  pub fn undo(&mut self, graph: &mut Graph, desired_state: &mut AnyState) {
+    let Some(edit) = self.next_undo() else { return };
+    edit.apply(graph, desired_state, true);
+    self.cursor -= 1;
+  }
+
+  pub fn redo(&mut self, graph: &mut Graph, desired_state: &mut AnyState) {
+    let Some(edit) = self.next_redo() else { return };
+    edit.apply(graph, desired_state, false);
+    self.cursor += 1;
+  }
+
+  /// States are packed for one state space, so they're useless once it's gone
+  pub fn forget_states(&mut self) {
+    let done_states = self.edits[.. self.cursor].iter().filter(|edit| !edit.is_graph_edit()).count();
+    self.cursor -= done_states;
+    self.edits.retain(Edit::is_graph_edit);
+  }
+
+  pub fn clear(&mut self) { *self = Self::default() }
+
+  pub fn edits(&self) -> &[Edit] { &self.edits }
+  pub fn cursor(&self) -> usize { self.cursor }
+}
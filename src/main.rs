@@ -1,25 +1,45 @@
-mod graph; mod state; mod mode;
+mod history;
+mod mode;
 
 use std::mem::take;
 use std::ops::RangeInclusive;
 use eframe::App;
-use eframe::egui::{Align2, CentralPanel, Color32, ComboBox, Context, DragValue, FontId, Id, LayerId, Order, Painter, Pos2, Sense, Stroke, Ui, Visuals, Widget, Window};
-use graph::Graph;
+use eframe::egui::{Align2, CentralPanel, CollapsingHeader, Color32, ComboBox, Context, DragValue, FontId, Id, Key, LayerId, Modifiers, Order, Painter, Pos2, RichText, ScrollArea, Sense, Shape, Stroke, TextEdit, Ui, Visuals, Widget, Window};
+use graph_explorer::{graph, state};
+use graph::{Graph, Invariants, NODE_RADIUS};
 use state::*;
 
+use crate::history::{Edit, History};
 use crate::mode::Modes;
 
-const NODE_RADIUS: f32 = 40.;
+// Length of the arrowheads on directed edges
+const ARROW_SIZE: f32 = 20.;
 
 struct GraphProgram {
   graph: Graph,
-  state_space: Option<StateData>,
+  state_space: Option< Box<dyn StateSpace> >,
   mode: Modes,
   max: u8,
+  rule: MoveRule,
+  // Explore one state per orbit of the graph's automorphisms
+  orbits_only: bool,
+  encoding: Encoding,
+  // Explored state spaces get saved to cache_dir and read back from it, see Cache
+  use_cache: bool,
+  cache_dir: String,
   graph_changed: bool,
+  // And the mode to enter once it's done
+  exploration: Option<(Exploration, usize)>,
+  // Why the last exploration came back empty handed
+  exploration_error: Option<&'static str>,
 
-  loaded_state: PackedState,
-  desired_state: PackedState,
+  loaded_state: AnyState,
+  desired_state: AnyState,
+  // Picked in analyze, solved towards in play
+  solve_target: Option<AnyState>,
+  history: History,
+  // And the neighbors they were computed from
+  invariants: (Invariants, Vec< Vec<usize> >),
 }
 impl GraphProgram {
   pub fn new() -> Self {
@@ -28,12 +48,22 @@ impl GraphProgram {
       graph: Graph::new(),
       mode: Modes::default(),
       max: 2,
+      rule: MoveRule::default(),
+      orbits_only: false,
+      encoding: Encoding::default(),
+      use_cache: true,
+      cache_dir: "cache".to_string(),
       graph_changed: false,
+      exploration: None,
+      exploration_error: None,
 
-      loaded_state: 0,
-      desired_state: 0,
+      loaded_state: AnyState::default(),
+      desired_state: AnyState::default(),
+      solve_target: None,
+      history: History::default(),
+      invariants: (Invariants::new(&[]), Vec::new()),
     };
-    let mode = Modes::new(&mut program, 0);
+    let mode = Modes::new(&program, 0);
     program.mode = mode;
     program
   }
@@ -41,14 +71,116 @@ impl GraphProgram {
   fn get_node_at(&self, pos: Pos2) -> Option<usize> {
     self.graph.node_at(pos, NODE_RADIUS)
   }
+
+  /// Contiguizes the graph, the history refers to nodes by index so it can't survive them moving
+  fn contiguize(&mut self) {
+    if !self.graph.contiguize_and_trim().is_empty() { self.history.clear() }
+  }
+
+  /// Swaps in the result of a graph operation, keeping the old graph around for undo
+  fn replace_graph(&mut self, name: &'static str, graph: Graph) {
+    let old = std::mem::replace(&mut self.graph, graph.clone());
+    self.history.record(Edit::Replace(name, Box::new((old, graph))));
+    self.graph_changed = true;
+  }
+
+  /// Loads `state` and records it in the history
+  fn move_to(&mut self, state: AnyState) {
+    if state == self.desired_state { return }
+    self.history.record(Edit::SetState(self.desired_state, state));
+    self.desired_state = state;
+  }
 }
 impl GraphProgram {
 
   fn settings_window(&mut self, ctx: &Context) {
     Window::new("Settings").show(ctx, |ui| {
       self.handle_max(ui);
+      self.handle_rule(ui);
+      self.handle_orbits(ui);
+      self.handle_encoding(ui);
+      self.handle_cache(ui);
       self.set_mode(ui);
+      self.exploration_status(ui);
       self.handle_mode_ui(ui);
+      self.history_ui(ui);
+      self.invariants_ui(ui);
+    });
+  }
+
+  // Graph edits can only be undone in blueprint, and states only by the modes that move them
+  fn can_apply(&self, edit: &Edit) -> bool {
+    if self.exploration.is_some() { return false }
+    if edit.is_graph_edit() { self.state_space.is_none() } else { self.mode.moves_state() }
+  }
+
+  fn undo(&mut self) {
+    let Some(edit) = self.history.next_undo() else { return };
+    if !self.can_apply(edit) { return }
+    self.history.undo(&mut self.graph, &mut self.desired_state);
+    self.graph_changed = true;
+  }
+
+  fn redo(&mut self) {
+    let Some(edit) = self.history.next_redo() else { return };
+    if !self.can_apply(edit) { return }
+    self.history.redo(&mut self.graph, &mut self.desired_state);
+    self.graph_changed = true;
+  }
+
+  fn handle_history_keys(&mut self, ctx: &Context) {
+    // Shift first, since the plain shortcut would match it too
+    if ctx.input_mut(|input| input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)) {
+      self.redo();
+    } else if ctx.input_mut(|input| input.consume_key(Modifiers::COMMAND, Key::Z)) {
+      self.undo();
+    }
+  }
+
+  fn history_ui(&mut self, ui: &mut Ui) {
+    CollapsingHeader::new("History").show(ui, |ui| {
+      ui.horizontal(|ui| {
+        if ui.button("Undo (Ctrl+Z)").clicked() { self.undo() }
+        if ui.button("Redo (Ctrl+Shift+Z)").clicked() { self.redo() }
+      });
+      ScrollArea::vertical().max_height(150.).stick_to_bottom(true).show(ui, |ui| {
+        for (idx, edit) in self.history.edits().iter().enumerate() {
+          // Undone edits are greyed out
+          let text = RichText::new(edit.to_string());
+          ui.label(if idx < self.history.cursor() { text } else { text.weak() });
+        }
+      });
+    });
+  }
+
+  /// Only recomputes when the edges changed, positions and values don't matter
+  fn refresh_invariants(&mut self) {
+    let mut graph = self.graph.clone();
+    graph.contiguize_and_trim();
+    // Directed graphs get the invariants of their underlying graph
+    let neighbors = if graph.directed { graph::underlying(&graph.get_neighbors()) } else { graph.get_neighbors() };
+    if neighbors != self.invariants.1 { self.invariants = (Invariants::new(&neighbors), neighbors) }
+  }
+
+  fn invariants_ui(&mut self, ui: &mut Ui) {
+    CollapsingHeader::new("Invariants").show(ui, |ui| {
+      let invariants = &self.invariants.0;
+      let or_infinite = |value: Option<usize>| value.map_or("∞".to_string(), |value| value.to_string());
+      let searched = |ui: &mut Ui, name: &str, value: Option<String>| match value {
+        Some(value) => { ui.label(format!("{name}: {value}")); }
+        None => { ui.label(format!("{name}: ?")).on_hover_text("Gave up, the graph is too big to search"); }
+      };
+
+      if self.graph.directed { ui.label("Of the underlying undirected graph"); }
+      let degrees = invariants.degrees.iter().map(usize::to_string).collect::<Vec<_>>().join(", ");
+      ui.label(format!("Degrees: {degrees}"));
+      ui.label(if invariants.components == 1 { "Connected".to_string() } else { format!("{} components", invariants.components) });
+      ui.label(format!("Diameter: {}", or_infinite(invariants.diameter)));
+      ui.label(format!("Girth: {}", or_infinite(invariants.girth)));
+      ui.label(if invariants.bipartite { "Bipartite" } else { "Not bipartite" });
+      searched(ui, "Independence number", invariants.independence.map(|value| value.to_string()));
+      searched(ui, "Domination number", invariants.domination.map(|value| value.to_string()));
+      searched(ui, "Automorphisms", invariants.automorphisms.map(|value| value.to_string()));
     });
   }
 
@@ -66,14 +198,84 @@ impl GraphProgram {
     // Right here is my complaint
     self.graph.correct_max(self.max);
     
-    if let Some(state_space) = &mut self.state_space {
-      *state_space = StateData::new(&mut self.graph, self.max).unwrap();
-      self.loaded_state = state_space.parse_vec(self.graph.export_state());
-      self.desired_state = self.loaded_state;
-    }
+    self.restart_exploration();
     self.graph_changed = true;
   }
 
+  fn handle_rule(&mut self, ui: &mut Ui) {
+    let old_rule = self.rule;
+    CollapsingHeader::new("Move Rule").show(ui, |ui| {
+      ComboBox::from_label("Neighborhood").selected_text(self.rule.neighborhood.to_string())
+        .show_ui(ui, |ui| {
+          ui.selectable_value(&mut self.rule.neighborhood, Neighborhood::Closed, "Closed");
+          ui.selectable_value(&mut self.rule.neighborhood, Neighborhood::Open, "Open");
+        })
+        .response.on_hover_text("Whether the center changes along with its neighbors")
+      ;
+      ui.horizontal(|ui| {
+        ui.add_enabled(
+          self.rule.neighborhood == Neighborhood::Closed,
+          DragValue::new(&mut self.rule.center_delta).range(RangeInclusive::new(-9, 9)),
+        );
+        ui.label("Center Delta");
+      });
+      ui.horizontal(|ui| {
+        DragValue::new(&mut self.rule.neighbor_delta).range(RangeInclusive::new(-9, 9)).ui(ui);
+        ui.label("Neighbor Delta");
+      });
+      ComboBox::from_label("Boundary").selected_text(self.rule.boundary.to_string())
+        .show_ui(ui, |ui| {
+          for boundary in Boundary::ALL {
+            ui.selectable_value(&mut self.rule.boundary, boundary, boundary.to_string());
+          }
+        })
+        .response.on_hover_text(
+          "What happens past 0 or max: clamp stops there, wrap goes around like Lights Out, reject disallows the move"
+        )
+      ;
+    });
+    if old_rule != self.rule { self.restart_exploration() }
+  }
+
+  fn handle_orbits(&mut self, ui: &mut Ui) {
+    let response = ui.checkbox(&mut self.orbits_only, "Orbits Only")
+      .on_hover_text("Only explore one state out of every set the graph's symmetries swap between")
+    ;
+    if response.changed() { self.restart_exploration() }
+  }
+
+  fn handle_encoding(&mut self, ui: &mut Ui) {
+    let old_encoding = self.encoding;
+    ComboBox::from_label("Encoding").selected_text(self.encoding.to_string())
+      .show_ui(ui, |ui| {
+        for encoding in Encoding::ALL {
+          ui.selectable_value(&mut self.encoding, encoding, encoding.to_string());
+        }
+      })
+      .response.on_hover_text("Mixed radix fits more nodes into each state when max + 1 isn't a power of two, but is slower")
+    ;
+    if old_encoding != self.encoding { self.restart_exploration() }
+  }
+
+  // Only matters for the next exploration, so nothing restarts
+  fn handle_cache(&mut self, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+      ui.checkbox(&mut self.use_cache, "Cache")
+        .on_hover_text("Save explored state spaces to this directory and read them back the next time")
+      ;
+      ui.add_enabled(self.use_cache, TextEdit::singleline(&mut self.cache_dir));
+    });
+  }
+
+  // Explores again with the current settings, if there's anything explored
+  fn restart_exploration(&mut self) {
+    if let Some((_, target)) = &self.exploration {
+      self.start_exploration(*target);
+    } else if self.state_space.is_some() {
+      self.start_exploration(self.mode.as_int());
+    }
+  }
+
   fn set_mode(&mut self, ui: &mut Ui) {
     let mut new_mode = self.mode.as_int();
     ComboBox::from_label("Mode").selected_text(format!("{}", self.mode))
@@ -83,33 +285,93 @@ impl GraphProgram {
         ui.selectable_value(&mut new_mode, 2, "Set");
         ui.selectable_value(&mut new_mode, 3, "Analyze");
         ui.selectable_value(&mut new_mode, 4, "Bubbles");
+        ui.selectable_value(&mut new_mode, 5, "State Graph");
       })
     ;
 
     if self.mode.as_int() == new_mode { return; }
 
+    if let Some((_, target)) = &mut self.exploration {
+      *target = new_mode;
+      return;
+    }
+
     // If new mode isn't blueprint
     if new_mode != 0 && self.state_space.is_none() {
-      
-      self.state_space = StateData::new(&mut self.graph, self.max);
-      if let Some(state_space) = &self.state_space {
-        self.loaded_state = state_space.parse_vec(self.graph.export_state());
-        self.desired_state = self.loaded_state;
-        self.graph_changed = true;
-      }
+      // We enter the mode once the worker is done, see Self::poll_exploration
+      self.start_exploration(new_mode);
+      return;
 
            // New mode is blueprint
     } else if new_mode == 0 && self.state_space.is_some() {
       self.state_space = None;
+      self.solve_target = None;
+      self.history.forget_states();
       self.graph_changed = true;
     }
 
     // We have to construct the mode after updating state_space
-    self.mode = Modes::new(&self, new_mode);
+    self.mode = Modes::new(self, new_mode);
+
+  }
 
+  /// Drops any current state space and explores a new one on a worker thread, staying in
+  /// blueprint until it's done
+  fn start_exploration(&mut self, target_mode: usize) {
+    if let Some((exploration, _)) = self.exploration.take() { exploration.cancel() }
+    self.exploration_error = None;
+    self.state_space = None;
+    self.solve_target = None;
+    self.history.forget_states();
+    self.contiguize();
+    if self.mode.as_int() != 0 { self.mode = Modes::new(self, 0); }
+    self.exploration = Some((Exploration::start(
+      &mut self.graph, self.max, self.rule, self.encoding, self.orbits_only,
+      self.use_cache.then(|| Cache::new(&self.cache_dir)),
+    ), target_mode));
+    self.graph_changed = true;
+  }
+
+  fn poll_exploration(&mut self) {
+    let Some((exploration, _)) = &self.exploration else { return };
+    if !exploration.is_finished() { return }
+    let (exploration, target_mode) = self.exploration.take().unwrap();
+
+    self.state_space = exploration.join();
+    if let Some(state_space) = &self.state_space {
+      self.loaded_state = state_space.parse_vec(self.graph.export_state());
+      self.desired_state = self.loaded_state;
+      self.graph_changed = true;
+      self.mode = Modes::new(self, target_mode);
+    } else {
+      // Cancelled explorations never get polled
+      self.exploration_error = Some(if self.graph.get_neighbors().is_empty() { "Graph has no nodes" }
+        else { "Too many states to explore" });
+    }
+  }
+
+  fn exploration_status(&mut self, ui: &mut Ui) {
+    let Some((exploration, _)) = &self.exploration else {
+      if let Some(error) = self.exploration_error { ui.colored_label(Color32::RED, error); }
+      return
+    };
+    let (phase, done, total) = exploration.progress().status();
+    let mut cancel = false;
+    ui.horizontal(|ui| {
+      match done.saturating_mul(100).checked_div(total) {
+        Some(percent) => ui.label(format!("{phase}: {percent}%")),
+        None => ui.label(format!("{phase}: {done} found")),
+      };
+      cancel = ui.button("Cancel").clicked();
+    });
+    // The worker notices on its own time, we don't need to wait for it
+    if cancel { self.exploration.take().unwrap().0.cancel(); }
+    ui.ctx().request_repaint();
   }
 
   fn handle_mode_ui(&mut self, ui: &mut Ui) {
+    // Don't let blueprint touch the graph while the worker is exploring it
+    if self.exploration.is_some() { return }
 
     let mut mode = take(&mut self.mode);
     mode.ui(self, ui);
@@ -117,9 +379,11 @@ impl GraphProgram {
 
     if let Some(state_space) = &self.state_space {
       let display = match state_space.classification_data(self.loaded_state).0 {
-        Classification::Valid => { "Valid" },
-        Classification::InvalidT1 => { "Invalid, Theorem 1" },
-        Classification::InvalidOther => { "Invalid, Unknown Theorem" },
+        Classification::Valid => { "Valid".to_string() },
+        Classification::InvalidTheorem(theorem) => {
+          format!("Invalid, {}", state_space.theorem_names()[theorem])
+        },
+        Classification::InvalidOther => { "Invalid, Unknown Theorem".to_string() },
       };
       ui.label(display);
     }
@@ -131,7 +395,8 @@ impl GraphProgram {
       for (idx, node) in self.graph.nodes.iter_mut() {
         let (has_zero, has_max) = state_space.neighborhood_zero_or_max(self.loaded_state, idx);
         node.color = 
-          if has_zero && has_max { Color32::RED } // Can't move
+          if state_space.rule().boundary == Boundary::Wrap { Color32::DARK_GREEN } // Values wrap, nothing blocks a move
+          else if has_zero && has_max { Color32::RED } // Can't move
           else if has_zero { Color32::ORANGE } // Can go up
           else if has_max { Color32::DARK_BLUE } // Can go down
           else { Color32::DARK_GREEN } // Free
@@ -153,17 +418,18 @@ impl GraphProgram {
       nodes.text(
         node.position,
         Align2::CENTER_CENTER,
-        &format!("{}", node.value),
+        format!("{}", node.value),
         FontId::new(NODE_RADIUS, eframe::egui::FontFamily::Proportional),
         Color32::WHITE
       );
 
       for neighbor in &node.neighbors {
-        if *neighbor > current {
-          lines.line_segment(
-            [node.position, self.graph.nodes.get(*neighbor).unwrap().position],
-            Stroke::new(4., Color32::WHITE)
-          );
+        let target = self.graph.nodes.get(*neighbor).unwrap().position;
+        if self.graph.directed {
+          lines.line_segment([node.position, target], Stroke::new(4., Color32::WHITE));
+          draw_arrowhead(&lines, node.position, target);
+        } else if *neighbor > current {
+          lines.line_segment([node.position, target], Stroke::new(4., Color32::WHITE));
         }
       }
     }
@@ -173,9 +439,11 @@ impl GraphProgram {
     CentralPanel::default().show(ctx, |ui| {
       let response = ui.allocate_rect(ui.clip_rect(), Sense::click_and_drag());
 
-      let mut mode = take(&mut self.mode);
-      mode.interactions(self, response);
-      self.mode = mode;
+      if self.exploration.is_none() {
+        let mut mode = take(&mut self.mode);
+        mode.interactions(self, response);
+        self.mode = mode;
+      }
 
       if self.desired_state != self.loaded_state
         && let Some(state_space) = &self.state_space
@@ -185,7 +453,7 @@ impl GraphProgram {
         self.graph_changed = true;
       }
 
-      if self.graph_changed { self.color_nodes(); self.graph_changed = false; }
+      if self.graph_changed { self.color_nodes(); self.refresh_invariants(); self.graph_changed = false; }
       self.draw_graph(ui);
     });
   }
@@ -194,6 +462,8 @@ impl GraphProgram {
 impl App for GraphProgram {
   fn update(&mut self, ctx: &eframe::egui::Context, _: &mut eframe::Frame) {
     ctx.set_visuals(Visuals::dark());
+    self.poll_exploration();
+    self.handle_history_keys(ctx);
     let mut mode = take(&mut self.mode);
     mode.tick(self);
     self.mode = mode;
@@ -205,6 +475,15 @@ impl App for GraphProgram {
   }
 }
 
+// Points at `to` from the edge of its node
+fn draw_arrowhead(painter: &Painter, from: Pos2, to: Pos2) {
+  let direction = (to - from).normalized();
+  let tip = to - direction * NODE_RADIUS;
+  let back = tip - direction * ARROW_SIZE;
+  let side = direction.rot90() * ARROW_SIZE / 2.;
+  painter.add(Shape::convex_polygon(vec![tip, back + side, back - side], Color32::WHITE, Stroke::NONE));
+}
+
 fn main() {
   let mut native_options = eframe::NativeOptions::default();
   native_options.viewport = native_options.viewport.with_title("Graph Application v3.3.3");
@@ -3,6 +3,7 @@ mod play;
 mod set;
 mod analyze;
 mod bubbles;
+mod state_graph;
 
 use eframe::egui::{Response, Ui};
 
@@ -16,16 +17,17 @@ trait Mode where Self: Sized {
   fn interactions(&mut self, _program: &mut GraphProgram, _response: Response) {}
 
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum Modes {
   Blueprint(blueprint::Blueprint),
   Play(play::Play),
   Set(set::Set),
   Analyze(analyze::Analyze),
   Bubbles(bubbles::Bubbles),
+  StateGraph(state_graph::StateGraph),
+  #[default]
   SwapState,
 }
-impl Default for Modes { fn default() -> Self { Self::SwapState } }
 impl std::fmt::Display for Modes {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     let str = match self {
@@ -34,6 +36,7 @@ impl std::fmt::Display for Modes {
       Self::Set(_) => "Set",
       Self::Analyze(_) => "Analyze",
       Self::Bubbles(_) => "Bubbles",
+      Self::StateGraph(_) => "State Graph",
       Self::SwapState => "Invalid Mode!!"
     };
     write!(f, "{}", str)
@@ -48,10 +51,14 @@ impl Modes {
       2 => Self::Set(set::Set::create(program)),
       3 => Self::Analyze(analyze::Analyze::create(program)),
       4 => Self::Bubbles(bubbles::Bubbles::create(program)),
+      5 => Self::StateGraph(state_graph::StateGraph::create(program)),
       _ => unreachable!()
     }
   }
   
+  /// Modes which move the loaded state around themselves, rather than showing states from a list
+  pub fn moves_state(&self) -> bool { matches!(self, Self::Play(_) | Self::Set(_)) }
+
   pub fn as_int(&self) -> usize {
     match self {
       Self::Blueprint(_) => 0,
@@ -59,6 +66,7 @@ impl Modes {
       Self::Set(_) => 2,
       Self::Analyze(_) => 3,
       Self::Bubbles(_) => 4,
+      Self::StateGraph(_) => 5,
       Self::SwapState => unreachable!(),
     }
   }
@@ -73,6 +81,7 @@ impl Modes {
       Self::Set(inside) => inside.ui(program, ui),
       Self::Analyze(inside) => inside.ui(program, ui),
       Self::Bubbles(inside) => inside.ui(program, ui),
+      Self::StateGraph(inside) => inside.ui(program, ui),
       Self::SwapState => unreachable!(),
     }
   }
@@ -84,6 +93,7 @@ impl Modes {
       Self::Set(inside) => inside.tick(program),
       Self::Analyze(inside) => inside.tick(program),
       Self::Bubbles(inside) => inside.tick(program),
+      Self::StateGraph(inside) => inside.tick(program),
       Self::SwapState => unreachable!(),
     }
   }
@@ -95,6 +105,7 @@ impl Modes {
       Self::Set(inside) => inside.interactions(program, response),
       Self::Analyze(inside) => inside.interactions(program, response),
       Self::Bubbles(inside) => inside.interactions(program, response),
+      Self::StateGraph(inside) => inside.interactions(program, response),
       Self::SwapState => unreachable!(),
     }
   }
@@ -4,11 +4,12 @@ use eframe::egui::{ComboBox, Context, Event, Pos2, Window};
 use num2words::{Num2Words, Lang::English};
 
 use super::common::*;
-use crate::state::{PackedState, Classification, frequency_analysis, parse_analysis};
+use crate::state::{AnyState, Classification, StateSpace, parse_analysis};
 
 #[derive(Debug)]
 pub struct Analyze {
   viewing_type: usize,
+  type_count: usize,
   viewing_length: usize,
   viewing: usize,
   parsed_analysis: Vec<Vec<u32>>,
@@ -20,7 +21,7 @@ impl Analyze {
       .show(ctx, |ui| {
         for (value, values) in self.parsed_analysis.iter().enumerate() {
           for (node_count, state_count) in values.iter().enumerate() {
-            ui.label(&format!(
+            ui.label(format!(
               "{state_count} {} {} {value}{}",
               if *state_count == 1 {"state has"} else {"states have"},
               Num2Words::new(node_count as f32).lang(English).to_words().unwrap(),
@@ -36,11 +37,16 @@ impl super::Mode for Analyze {
   fn create(program: &GraphProgram) -> Self {
     let (viewing_type, idx) = if let Some(state_space) = &program.state_space {
       let (classification, idx) = state_space.classification_data(program.loaded_state);
-      (classification as usize, idx)
-    } else { (3, 0) };
+      let viewing_type = view_types(state_space.as_ref()).iter()
+        .position(|(_, lists)| lists == &[classification])
+        .unwrap()
+      ;
+      (viewing_type, idx)
+    } else { (0, 0) };
 
     Self {
       viewing_type,
+      type_count: 0,
       viewing_length: 0,
       // Wow I hate that I did this off by one nonsense
       viewing: idx + 1,
@@ -54,32 +60,18 @@ impl super::Mode for Analyze {
 
     // Identify view type
     let old_type = self.viewing_type;
-    let names = [
-      "All Valid",
-      "Lonely States",
-      "Other Invalid",
-      "All Invalid",
-    ];
-    ComboBox::from_label("Type").selected_text(format!("{}", names[old_type]))
+    let view_types = view_types(state_space.as_ref());
+    self.type_count = view_types.len();
+    ComboBox::from_label("Type").selected_text(&view_types[old_type].0)
       .show_ui(ui, |ui| {
-        ui.selectable_value(&mut self.viewing_type, 0, names[0]);
-        ui.selectable_value(&mut self.viewing_type, 1, names[1]);
-        ui.selectable_value(&mut self.viewing_type, 2, names[2]);
-        ui.selectable_value(&mut self.viewing_type, 3, names[3]);
+        for (idx, (name, _)) in view_types.iter().enumerate() {
+          ui.selectable_value(&mut self.viewing_type, idx, name);
+        }
       })
     ;
-    let focused_states = match self.viewing_type {
-      0 => state_space.get_list(Classification::Valid),
-      1 => state_space.get_list(Classification::InvalidT1),
-      2 => state_space.get_list(Classification::InvalidOther),
-      3 => &combine(
-        state_space.get_list(Classification::InvalidOther), 
-        state_space.get_list(Classification::InvalidT1)
-      ),
-      _ => unreachable!()
-    };
-
-    self.viewing_length = focused_states.len();
+    let focused_lists = &view_types[self.viewing_type].1;
+
+    self.viewing_length = focused_lists.iter().map(|list| state_space.list_len(*list)).sum();
 
     // Identify view idx
     ui.horizontal(|ui| {
@@ -92,17 +84,25 @@ impl super::Mode for Analyze {
     });
 
     if self.parsed_analysis.is_empty() || old_type != self.viewing_type {
-      let analysis = frequency_analysis(focused_states, state_space.length(), program.max);
-      self.parsed_analysis = parse_analysis(analysis, program.max, state_space.length() as u8);
+      let analysis = state_space.frequency_analysis(focused_lists);
+      self.parsed_analysis = parse_analysis(analysis, state_space.base() - 1, state_space.length() as u8);
     }
 
     // Load current viewing state
-    if let Some(state) = focused_states.get(self.viewing - 1) {
-      program.desired_state = *state;
+    if let Some(state) = focused_state(state_space.as_ref(), focused_lists, self.viewing - 1) {
+      program.desired_state = state;
+    }
+    // Lists only hold one state per orbit when exploring orbits
+    if let Some(orbit_size) = state_space.orbit_size(program.desired_state) {
+      ui.label(format!("Orbit of {orbit_size} States"));
     }
 
-    let total = (state_space.base as usize).pow(state_space.length() as u32);
-    ui.label(&format!("{total} Total State Count"));
+    let total = state_space.bases().iter().map(|base| *base as usize).product::<usize>();
+    ui.label(format!("{total} Total State Count"));
+
+    if ui.button("Set as Solve Target").clicked() {
+      program.solve_target = Some(program.desired_state);
+    }
 
     self.draw_analysis_window(ui.ctx());
   }
@@ -141,22 +141,41 @@ impl super::Mode for Analyze {
 
     }
     
-    if up_pressed {
-      self.viewing_type = if self.viewing_type == 0 { 3 } else { self.viewing_type - 1 };
-    }
-    
-    if down_pressed {
-      self.viewing_type = (self.viewing_type + 1) % 4;
+    if self.type_count != 0 {
+      if up_pressed {
+        self.viewing_type = if self.viewing_type == 0 { self.type_count - 1 } else { self.viewing_type - 1 };
+      }
+
+      if down_pressed {
+        self.viewing_type = (self.viewing_type + 1) % self.type_count;
+      }
     }
 
   }
 
 }
 
-fn combine<'a>(a: &'a [PackedState], b: &'a [PackedState]) -> Vec<PackedState> {
-  let mut out = Vec::with_capacity(a.len() + b.len());
-  out.extend_from_slice(a);
-  out.extend_from_slice(b);
-  out
+// Names and lists for the Type combo, one entry per registered theorem
+pub(super) fn view_types(state_space: &dyn StateSpace) -> Vec<(String, Vec<Classification>)> {
+  let theorems = state_space.theorem_names();
+  let mut types = vec![("All Valid".to_string(), vec![Classification::Valid])];
+  for (idx, name) in theorems.iter().enumerate() {
+    types.push((name.to_string(), vec![Classification::InvalidTheorem(idx)]));
+  }
+  types.push(("Other Invalid".to_string(), vec![Classification::InvalidOther]));
+  let mut all_invalid = vec![Classification::InvalidOther];
+  all_invalid.extend((0 .. theorems.len()).map(Classification::InvalidTheorem));
+  types.push(("All Invalid".to_string(), all_invalid));
+  types
+}
+
+// Index into the lists as if they were concatenated
+pub(super) fn focused_state(state_space: &dyn StateSpace, lists: &[Classification], mut idx: usize) -> Option<AnyState> {
+  for list in lists {
+    let len = state_space.list_len(*list);
+    if idx < len { return state_space.list_state(*list, idx) }
+    idx -= len;
+  }
+  None
 }
 
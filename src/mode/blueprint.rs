@@ -1,19 +1,47 @@
 use std::ops::RangeInclusive;
 
-use crate::{NODE_RADIUS, graph::Graph};
+use crate::history::Edit;
+use crate::graph::{Graph, GraphType, from_digraph6, from_graph6, from_project, to_digraph6, to_graph6, to_project};
+use crate::graph::layout::{self, Layout};
+use crate::graph::ops;
 
 use super::common::*;
-use eframe::egui::{Align2, Area, Color32, Context, Event, FontId, LayerId, Order, Painter, Pos2, Rect, RichText, Stroke, Vec2, Window};
+use eframe::egui::{Align2, Area, Button, CollapsingHeader, Color32, Context, Event, FontId, LayerId, Order, Painter, Pos2, RichText, Stroke, Window};
+
+type BinaryOperation = fn(&Graph, &Graph) -> Graph;
+
+// How far nodes can move per frame when a force layout starts animating, it cools down from there
+const ANIMATION_TEMPERATURE: f32 = 20.;
 
 #[derive(Debug)]
 pub struct Blueprint {
   selected: Option<usize>,
+  // Where the selected node was before being dragged
+  drag_origin: Option<Pos2>,
   action_cd: usize,
-  action: usize, // 1 is save, 2 is load
+  action: usize, // 1 is save, 2 is load, 3 is save as
   can_drag: bool,
 
   loading_screen: bool,
   load_n: usize,
+  load_m: usize,
+  load_seed: u64,
+  graph6_input: String,
+  graph6_invalid: bool,
+  project_path: String,
+  project_error: Option<String>,
+  // Second graph for the binary operations
+  other_graph: String,
+  other_invalid: bool,
+  // The graph before the force layout started, and how far nodes can move this frame
+  animation: Option<(Graph, f32)>,
+  layout_failed: bool,
+  // Whose max the Node Max panel edits, follows the last clicked node
+  max_node: usize,
+  // The node clicked before max_node, Subdivide and Contract act on the two
+  previous_node: usize,
+  // The graph before the Node Max value started being dragged
+  max_origin: Option< Box<Graph> >,
 }
 impl Blueprint {
   fn load_menu(&mut self, program: &mut GraphProgram, ctx: &Context) {
@@ -42,42 +70,67 @@ impl Blueprint {
         ui.vertical_centered(|ui| {
           ui.label(RichText::new("Load Options").size(30.).strong());
           
-          DragValue::new(&mut self.load_n)
-            .range(RangeInclusive::new(1, 15))
-            .speed(0.1)
-            .ui(ui)
-          ;
-
-          if ui.button("Path").clicked() {
-            program.graph = GraphType::Path(self.load_n).new(ctx.content_rect());
-            program.graph_changed = true;
-            self.loading_screen = false;
-          }
-
-          if ui.button("Cycle").clicked() {
-            program.graph = GraphType::Cycle(self.load_n).new(ctx.content_rect());
-            program.graph_changed = true;
-            self.loading_screen = false;
+          ui.horizontal(|ui| {
+            ui.label("n");
+            DragValue::new(&mut self.load_n).range(RangeInclusive::new(0, 15)).speed(0.1).ui(ui);
+            ui.label("m");
+            DragValue::new(&mut self.load_m).range(RangeInclusive::new(0, 15)).speed(0.1).ui(ui);
+            ui.label("Seed");
+            DragValue::new(&mut self.load_seed).ui(ui);
+          });
+
+          let (n, m) = (self.load_n, self.load_m);
+          let families = [
+            ("Path", GraphType::Path(n)),
+            ("Cycle", GraphType::Cycle(n)),
+            ("Complete", GraphType::Complete(n)),
+            ("Wheel", GraphType::Wheel(n)),
+            ("Star", GraphType::Star(n)),
+            ("Grid n×m", GraphType::Grid(n, m)),
+            ("Ladder", GraphType::Ladder(n)),
+            ("Prism", GraphType::Prism(n)),
+            ("Hypercube", GraphType::Hypercube(n)),
+            ("K n,m", GraphType::CompleteBipartite(n, m)),
+            ("Petersen", GraphType::Petersen),
+            ("GP(n, m)", GraphType::GeneralizedPetersen(n, m)),
+            ("Friendship", GraphType::Friendship(n)),
+            ("Random Tree", GraphType::RandomTree(n, self.load_seed)),
+          ];
+          ui.horizontal_wrapped(|ui| {
+            for (name, family) in families {
+              if ui.add_enabled(family.is_valid(), Button::new(name)).clicked() {
+                program.graph = family.build(ctx.content_rect());
+                program.graph_changed = true;
+                program.history.clear();
+                self.animation = None;
+                self.loading_screen = false;
+              }
+            }
+          });
+
+          ui.separator();
+          ui.label("graph6 or digraph6");
+          ui.text_edit_singleline(&mut self.graph6_input);
+          if ui.button("Import").clicked() {
+            let family = from_digraph6(&self.graph6_input).map(GraphType::ImportedDirected)
+              .or_else(|| from_graph6(&self.graph6_input).map(GraphType::Imported))
+            ;
+            if let Some(family) = family {
+              program.graph = family.build(ctx.content_rect());
+              program.graph_changed = true;
+              program.history.clear();
+              self.animation = None;
+              self.loading_screen = false;
+              self.graph6_invalid = false;
+              self.action_cd = 300;
+              self.action = 2;
+            } else { self.graph6_invalid = true }
           }
-
-          if ui.button("Complete").clicked() {
-            program.graph = GraphType::Complete(self.load_n).new(ctx.content_rect());
-            program.graph_changed = true;
-            self.loading_screen = false;
-          }
-
-          if ui.button("Wheel").clicked() {
-            program.graph = GraphType::Wheel(self.load_n).new(ctx.content_rect());
-            program.graph_changed = true;
-            self.loading_screen = false;
-          }
-
-          if ui.button("Star").clicked() {
-            program.graph = GraphType::Star(self.load_n).new(ctx.content_rect());
-            program.graph_changed = true;
-            self.loading_screen = false;
+          if self.graph6_invalid {
+            ui.colored_label(Color32::RED, "Invalid graph6 or digraph6 string");
           }
 
+          ui.separator();
           if ui.button("Cancel").clicked() {
             self.loading_screen = false;
           }
@@ -91,17 +144,217 @@ impl Blueprint {
   }
 
 }
+impl Blueprint {
+  // Save As and Open for project files, which unlike graph6 keep positions, values and max
+  fn project_files(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+      ui.text_edit_singleline(&mut self.project_path);
+      if ui.button("Save As").clicked() {
+        program.contiguize();
+        let project = to_project(&program.graph, program.max);
+        match std::fs::write(&self.project_path, project) {
+          Ok(()) => {
+            self.project_error = None;
+            self.action_cd = 300;
+            self.action = 3;
+          }
+          Err(error) => self.project_error = Some(format!("Couldn't save: {error}")),
+        }
+      }
+      if ui.button("Open").clicked() {
+        match std::fs::read_to_string(&self.project_path) {
+          Ok(input) => if let Some((graph, max)) = from_project(&input) {
+            program.graph = graph;
+            program.max = max;
+            program.history.clear();
+            self.animation = None;
+            program.graph_changed = true;
+            self.selected = None;
+            self.project_error = None;
+            self.action_cd = 300;
+            self.action = 2;
+          } else { self.project_error = Some("Invalid project file".to_string()) },
+          Err(error) => self.project_error = Some(format!("Couldn't open: {error}")),
+        }
+      }
+    });
+    if let Some(error) = &self.project_error {
+      ui.colored_label(Color32::RED, error);
+    }
+  }
+}
+impl Blueprint {
+  // Blank means the current graph, otherwise a family spec or graph6
+  fn other_graph(&self, program: &GraphProgram, ui: &Ui) -> Option<Graph> {
+    let spec = self.other_graph.trim();
+    if spec.is_empty() { return Some(program.graph.clone()) }
+    let family = GraphType::from_spec(spec).or_else(|| from_graph6(spec).map(GraphType::Imported))?;
+    Some(family.build(ui.ctx().content_rect()))
+  }
+
+  // Undirected edges become an arc each way, and arcs become edges
+  fn directed(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
+    let mut directed = program.graph.directed;
+    if !ui.checkbox(&mut directed, "Directed").changed() { return }
+    self.finish_animation(program);
+    let mut graph = program.graph.clone();
+    if directed { graph.directed = true } else { graph.make_undirected() }
+    program.replace_graph(if directed { "Directed" } else { "Undirected" }, graph);
+  }
+
+  fn operations(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
+    CollapsingHeader::new("Operations").show(ui, |ui| {
+      if program.graph.directed {
+        ui.label("Operations only work on undirected graphs");
+        return;
+      }
+      ui.horizontal(|ui| {
+        if ui.button("Complement").clicked() {
+          program.replace_graph("Complement", ops::complement(&program.graph));
+        }
+        if ui.button("Line Graph").clicked() {
+          program.replace_graph("Line Graph", ops::line_graph(&program.graph));
+        }
+      });
+
+      ui.horizontal(|ui| {
+        ui.label("With");
+        ui.text_edit_singleline(&mut self.other_graph)
+          .on_hover_text("A family spec like path:3 or a graph6 string, blank for a copy of this graph");
+      });
+      let binary: [(&'static str, BinaryOperation); 5] = [
+        ("Cartesian", ops::cartesian_product),
+        ("Tensor", ops::tensor_product),
+        ("Strong", ops::strong_product),
+        ("Join", ops::join),
+        ("Union", ops::disjoint_union),
+      ];
+      ui.horizontal_wrapped(|ui| {
+        for (name, operation) in binary {
+          if !ui.button(name).clicked() { continue }
+          match self.other_graph(program, ui) {
+            Some(other) => {
+              program.replace_graph(name, operation(&program.graph, &other));
+              self.other_invalid = false;
+            }
+            None => self.other_invalid = true,
+          }
+        }
+      });
+      if self.other_invalid {
+        ui.colored_label(Color32::RED, "Invalid family spec or graph6 string");
+      }
+
+      ui.separator();
+      let (node1, node2) = (self.previous_node, self.max_node);
+      let picked = node1 != node2 && program.graph.nodes.is_occupied(node1) && program.graph.nodes.is_occupied(node2);
+      ui.horizontal(|ui| {
+        if ui.add_enabled(picked && program.graph.has_connection(node1, node2), Button::new("Subdivide")).clicked()
+          && let Some(graph) = ops::subdivide(&program.graph, node1, node2)
+        { program.replace_graph("Subdivide", graph) }
+        if ui.add_enabled(picked, Button::new("Contract")).clicked()
+          && let Some(graph) = ops::contract(&program.graph, node1, node2)
+        { program.replace_graph("Contract", graph) }
+        if picked { ui.label(format!("Nodes {node1} and {node2}")) }
+        else { ui.label("Click two nodes to pick them") }
+      });
+      ui.label("Or hold S or C when connecting two nodes");
+    });
+  }
+}
+impl Blueprint {
+  fn layouts(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
+    CollapsingHeader::new("Layout").show(ui, |ui| {
+      ui.horizontal_wrapped(|ui| {
+        for layout in Layout::ALL {
+          if !ui.button(layout.to_string()).clicked() { continue }
+          self.finish_animation(program);
+          let mut graph = program.graph.clone();
+          self.layout_failed = !layout.apply(&mut graph, ui.ctx().content_rect());
+          if !self.layout_failed { program.replace_graph("Layout", graph) }
+        }
+      });
+
+      let mut animate = self.animation.is_some();
+      if ui.checkbox(&mut animate, "Animate Force Directed").changed() {
+        if animate {
+          self.animation = Some((program.graph.clone(), ANIMATION_TEMPERATURE));
+        } else { self.finish_animation(program) }
+      }
+      if self.layout_failed {
+        ui.colored_label(Color32::RED, "Graph isn't bipartite");
+      }
+    });
+    if self.animation.is_some() { ui.ctx().request_repaint() }
+  }
+
+  // Records the whole animation as one edit
+  fn finish_animation(&mut self, program: &mut GraphProgram) {
+    let Some((start, _)) = self.animation.take() else { return };
+    program.history.record(Edit::Replace("Force Directed", Box::new((start, program.graph.clone()))));
+  }
+}
+impl Blueprint {
+  // Lets a node override the graph's max, lowering it clamps the node's value
+  fn node_max(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
+    CollapsingHeader::new("Node Max").show(ui, |ui| {
+      let Some(node) = program.graph.nodes.get(self.max_node) else {
+        self.finish_max(program);
+        ui.label("Click a node to give it its own max");
+        return;
+      };
+      let mut own = node.max.is_some();
+      let mut max = node.max.unwrap_or(program.max);
+      let (toggled, value) = ui.horizontal(|ui| {
+        ui.label(format!("Node {}", self.max_node));
+        let toggled = ui.checkbox(&mut own, "Own Max").changed();
+        (toggled, ui.add_enabled(own, DragValue::new(&mut max).range(0 ..= 9)))
+      }).inner;
+
+      if toggled || value.changed() {
+        // Starts from the graph before the drag, so values clamped along the way come back
+        let mut graph = (**self.max_origin.get_or_insert_with(|| Box::new(program.graph.clone()))).clone();
+        if let Some(node) = graph.nodes.get_mut(self.max_node) { node.max = own.then_some(max) }
+        graph.correct_max(program.max);
+        program.graph = graph;
+        program.graph_changed = true;
+      }
+      // Once the drag stops or typing loses focus, anything else changes it in one go
+      if !(value.dragged() || value.has_focus()) { self.finish_max(program) }
+    });
+  }
+
+  // Records the whole drag as one edit
+  fn finish_max(&mut self, program: &mut GraphProgram) {
+    let Some(origin) = self.max_origin.take() else { return };
+    program.history.record(Edit::Replace("Set Max", Box::new((*origin, program.graph.clone()))));
+  }
+}
 impl super::Mode for Blueprint {
 
   fn create(_program: &GraphProgram) -> Self {
     Self {
       selected: None,
+      drag_origin: None,
       action_cd: 0,
       action: 0,
       can_drag: false,
 
       loading_screen: false,
-      load_n: 1,
+      load_n: 3,
+      load_m: 2,
+      load_seed: 0,
+      graph6_input: String::new(),
+      graph6_invalid: false,
+      project_path: "untitled.graph".to_string(),
+      project_error: None,
+      other_graph: String::new(),
+      other_invalid: false,
+      animation: None,
+      layout_failed: false,
+      max_node: 0,
+      previous_node: 0,
+      max_origin: None,
     }
   }
 
@@ -109,22 +362,29 @@ impl super::Mode for Blueprint {
     if self.loading_screen { self.load_menu(program, ui.ctx()); return } 
     
     ui.checkbox(&mut self.can_drag, "Drag (Space to Toggle)");
+    self.directed(program, ui);
 
     ui.horizontal(|ui| {
       if ui.button("Save").clicked() {
         // there is certainly a cheaper solution, but atm not my problem
-        program.graph.contiguize_and_trim();
-        ui.ctx().copy_text( to_graph6( program.graph.get_neighbors() ) );
+        program.contiguize();
+        let neighbors = program.graph.get_neighbors();
+        ui.ctx().copy_text( if program.graph.directed { to_digraph6(neighbors) } else { to_graph6(neighbors) } );
         self.action_cd = 300;
         self.action = 1;
       }
       if ui.button("Load").clicked() { self.loading_screen = true }
     });
+    self.project_files(program, ui);
+    self.operations(program, ui);
+    self.layouts(program, ui);
+    self.node_max(program, ui);
     
     if self.action_cd > 0 {
       let message = match self.action {
         1 => "Copied to Clipboard!",
         2 => "Loaded!",
+        3 => "Saved!",
         _ => unimplemented!()
       };
       ui.label(message);
@@ -132,13 +392,23 @@ impl super::Mode for Blueprint {
 
   }
 
-  fn tick(&mut self, _program: &mut GraphProgram) {
+  fn tick(&mut self, program: &mut GraphProgram) {
     self.action_cd = self.action_cd.saturating_sub(1);
+
+    // Animated layouts aren't fitted to the screen, since the graph would jump around every frame
+    if !self.loading_screen && let Some((_, temperature)) = &mut self.animation {
+      let moved = layout::force_step_graph(&mut program.graph, *temperature);
+      *temperature *= 0.99;
+      if moved < 0.5 { self.finish_animation(program) }
+    }
   }
 
   fn interactions(&mut self, program: &mut GraphProgram, response: Response) {
     if self.loading_screen { return }
 
+    // Editing a graph that's still moving would tangle up the history
+    if response.ctx.input(|input| input.pointer.any_pressed()) { self.finish_animation(program) }
+
     let Some(pos) = response.hover_pos() else { return };
     let hovering = program.get_node_at(pos);
 
@@ -151,11 +421,11 @@ impl super::Mode for Blueprint {
       }
 
       // Delete hovering on right click
-      if input.pointer.secondary_down() {
-        if let Some(remove) = hovering { 
-          program.graph.remove(remove);
-          program.graph_changed = true;
-        }
+      if input.pointer.secondary_down() && let Some(remove) = hovering {
+        let in_neighbors = program.graph.in_neighbors(remove);
+        let removed = program.graph.remove(remove);
+        program.history.record(Edit::RemoveNode(remove, removed, in_neighbors));
+        program.graph_changed = true;
       }
 
       // Select/Create on left click
@@ -164,37 +434,66 @@ impl super::Mode for Blueprint {
         self.selected = hovering;
         // Or we create a node
         if self.selected.is_none() {
-          self.selected = Some(program.graph.add_node(pos));
+          let node = program.graph.add_node(pos);
+          program.history.record(Edit::AddNode(node, pos));
+          self.selected = Some(node);
           program.graph_changed = true;
         }
+        if let Some(node) = self.selected && node != self.max_node {
+          self.previous_node = std::mem::replace(&mut self.max_node, node);
+        }
+        self.drag_origin = self.selected.and_then(|node| program.graph.nodes.get(node)).map(|node| node.position);
         // Or do nothing if we're not touching it but too close to make one??
       }
 
     }); 
  
     if self.can_drag {
-      if response.dragged_by(PointerButton::Primary) && let Some(node) = self.selected {
-        program.graph.nodes.get_mut(node).unwrap().position = pos;
+      // The node can be gone if an undo removed it mid drag
+      if response.dragged_by(PointerButton::Primary)
+        && let Some(node) = self.selected
+        && let Some(node) = program.graph.nodes.get_mut(node)
+      {
+        node.position = pos;
+      }
+      if response.drag_stopped_by(PointerButton::Primary)
+        && let Some(node) = self.selected
+        && let Some(origin) = self.drag_origin.take()
+        && let Some(position) = program.graph.nodes.get(node).map(|node| node.position)
+        && origin != position
+      {
+        program.history.record(Edit::MoveNode(node, origin, position));
       }
     } else {
 
       // Draw line from selected node to mouse
-      if response.dragged_by(PointerButton::Primary) && let Some(node) = self.selected {
+      if response.dragged_by(PointerButton::Primary)
+        && let Some(node) = self.selected
+        && let Some(origin) = program.graph.nodes.get(node).map(|node| node.position)
+      {
         let line_color = if let Some(hovering) = hovering 
           && program.graph.has_connection(node, hovering) 
         { Color32::RED } else { Color32:: WHITE };
-        let origin = program.graph.nodes.get(node).unwrap().position;
         let lines = Painter::new(response.ctx.clone(), LayerId::new(Order::Background, Id::new("Lines")), response.interact_rect);
         lines.line_segment([pos, origin], Stroke::new(4., line_color));
       }
       
       if response.drag_stopped_by(PointerButton::Primary) {
+        // Only undirected edges get subdivided or contracted, see the Operations section
+        let undirected = !program.graph.directed;
+        let (subdivide, contract) = response.ctx.input(|input| (input.key_down(Key::S), input.key_down(Key::C)));
+        let (subdivide, contract) = (subdivide && undirected, contract && undirected);
         if let Some(node1) = self.selected && let Some(node2) = hovering && node1 != node2 {
+          if contract {
+            if let Some(graph) = ops::contract(&program.graph, node1, node2) { program.replace_graph("Contract", graph) }
+          } else if subdivide {
+            if let Some(graph) = ops::subdivide(&program.graph, node1, node2) { program.replace_graph("Subdivide", graph) }
           // If we fail to add unique connection, remove the existing one.
-          if !program.graph.attempt_unique_connection(node1, node2) {
-            program.graph.remove_connection(node1, node2);
-
-          };
+          } else if program.graph.attempt_unique_connection(node1, node2) {
+            program.history.record(Edit::Connect(node1, node2));
+          } else if program.graph.remove_connection(node1, node2) {
+            program.history.record(Edit::Disconnect(node1, node2));
+          }
           program.graph_changed = true;
         }
         self.selected = None;
@@ -204,157 +503,3 @@ impl super::Mode for Blueprint {
   }
 
 }
-
-enum GraphType {
-  Path(usize),
-  Cycle(usize),
-  Complete(usize),
-  Wheel(usize),
-  Star(usize),
-}
-impl GraphType {
-  // Add a max size to prevent from going off screen
-  fn new(self, space: Rect) -> Graph {
-    let mut graph = Graph::new();
-    match self {
-      Self::Path(n) => {
-        let node_size = NODE_RADIUS * 3.;
-        let step = Vec2::new(node_size, 0.);
-        let start = space.center() - Vec2::new((node_size * n as f32 - 1.) / 2., 0.);
-        let mut cur_pos = start;
-
-        graph.add_node(cur_pos);
-        if n == 1 { return graph }
-        graph.unchecked_directed_connection(0, 1);
-
-        cur_pos += step;
-        for i in 1 .. n - 1 {
-          graph.add_node(cur_pos);
-          graph.unchecked_directed_connection(i, i + 1);
-          graph.unchecked_directed_connection(i, i - 1);
-          cur_pos += step;
-        }
-
-        graph.add_node(cur_pos);
-        graph.unchecked_directed_connection(n - 1, n - 2);
-      }
-      Self::Cycle(n) => {
-        let big_radius = NODE_RADIUS * n as f32;
-        let points = points_on_circle(n, space.center(), big_radius);
-        // for (i, &point) in points.iter().enumerate() {
-        for point in points {
-          graph.add_node(point);
-        }
-        for i in 1 .. n - 1 {
-          graph.attempt_unique_connection(i, (i + 1) % n);
-          graph.attempt_unique_connection(i, i - 1);
-        }
-        graph.attempt_unique_connection(0, n - 1);
-      }
-      Self::Complete(n) => {
-        let big_radius = NODE_RADIUS * n as f32;
-        let points = points_on_circle(n, space.center(), big_radius);
-        for point in points {
-          graph.add_node(point);
-        }
-        for i in 0 .. n {
-          for j in 0 .. n {
-            // Not optimal but fast :sunglasses:
-            graph.attempt_unique_connection(i, j);
-          }
-        }
-        graph.attempt_unique_connection(0, n - 1);
-      }
-      Self::Wheel(n) => {
-        graph.add_node(space.center());
-        let big_radius = (NODE_RADIUS * n as f32).max(200.);
-        let points = points_on_circle(n, space.center(), big_radius);
-        for point in points {
-          graph.add_node(point);
-        }
-        // This logic feels wrong but it works
-        for i in 1 .. n + 1 {
-          graph.attempt_unique_connection(i, (i + 1) % n);
-          graph.attempt_unique_connection(i, i - 1);
-          graph.attempt_unique_connection(0, i);
-        }
-        graph.attempt_unique_connection(1, n);
-      }
-      Self::Star(n) => {
-        graph.add_node(space.center());
-        let big_radius = (NODE_RADIUS * n as f32).max(200.);
-        let points = points_on_circle(n, space.center(), big_radius);
-        for point in points { graph.add_node(point); }
-        for i in 1 .. n + 1 {
-          graph.attempt_unique_connection(0, i);
-        }
-      }
-    };
-    graph
-  }
-}
-
-use std::f32::consts::TAU;
-
-fn points_on_circle( n: usize, center: Pos2, radius: f32) -> Vec<Pos2> {
-  (0..n).map(|i| {
-    let theta = TAU * i as f32 / n as f32;
-    Pos2::new(
-      center.x + radius * theta.cos(),
-      center.y + radius * theta.sin(),
-    )
-  }).collect()
-}
-
-/// Serialize an undirected simple graph into graph6 format.
-/// 
-/// `adj[i]` contains the neighbors of vertex `i`.
-/// Assumes:
-/// - vertices are 0..n-1
-/// - no self-loops
-/// - undirected (i in adj[j] iff j in adj[i])
-
-// Visualizer which allows graph6 imports
-// https://houseofgraphs.org/draw_graph
-pub fn to_graph6(adj: Vec< Vec<usize> >) -> String {
-  let n = adj.len();
-  assert!(n <= 62, "This implementation supports n <= 62");
-
-  // Encode number of vertices
-  let mut output = String::new();
-  output.push((n as u8 + 63) as char);
-
-  // Build adjacency lookup for fast edge testing
-  let mut has_edge = vec![vec![false; n]; n];
-  for (u, neighbors) in adj.iter().enumerate() {
-    for &v in neighbors {
-      assert!(u != v, "Self-loops are not allowed");
-      has_edge[u][v] = true;
-      has_edge[v][u] = true;
-    }
-  }
-
-  // Collect upper-triangle bits in graph6 order
-  let mut bits: Vec<u8> = Vec::new();
-  for j in 1..n {
-    for i in 0..j {
-      bits.push(if has_edge[i][j] { 1 } else { 0 });
-    }
-  }
-
-  // Pad with zeros to multiple of 6
-  while bits.len() % 6 != 0 {
-    bits.push(0);
-  }
-
-  // Encode bits in chunks of 6
-  for chunk in bits.chunks(6) {
-    let mut value = 0u8;
-    for &bit in chunk {
-      value = (value << 1) | bit;
-    }
-    output.push((value + 63) as char);
-  }
-
-  output
-}
@@ -1,9 +1,9 @@
 use std::ops::RangeInclusive;
 
-use eframe::egui::Event;
+use eframe::egui::{Event, ScrollArea};
 
 use super::common::*;
-use crate::state::PackedState;
+use crate::state::{AnyState, BubbleDag};
 
 #[derive(Debug)]
 pub struct Bubbles {
@@ -13,10 +13,53 @@ pub struct Bubbles {
   state_length: usize,
 }
 impl Bubbles {
-  pub fn assign_self(&mut self, program: &mut GraphProgram, state: PackedState) {
+  fn bubble_name(dag: &BubbleDag, id: usize) -> String {
+    let (bubble_idx, state_idx) = dag.location(id);
+    if dag.is_single(id) { format!("Size 1 #{}", state_idx + 1) } else { format!("Bubble {}", bubble_idx + 1) }
+  }
+
+  // Lists the bubbles in `ids` as buttons, returns the one clicked
+  fn bubble_list(ui: &mut Ui, dag: &BubbleDag, label: &str, ids: &[usize]) -> Option<usize> {
+    let mut clicked = None;
+    ui.label(format!("{label} {} bubbles", ids.len()));
+    ScrollArea::vertical().id_salt(label).max_height(80.).show(ui, |ui| {
+      ui.horizontal_wrapped(|ui| {
+        for id in ids {
+          if ui.button(Self::bubble_name(dag, *id)).clicked() { clicked = Some(*id) }
+        }
+      });
+    });
+    clicked
+  }
+
+  fn draw_dag(&mut self, program: &GraphProgram, ui: &mut Ui) {
+    let Some(state_space) = program.state_space.as_ref() else { return };
+    let dag = state_space.bubble_dag();
+    let id = dag.id(self.bubble - 1, self.state - 1);
+
+    ui.separator();
+    ui.label(format!(
+      "{} Groups, {} Sources, {} Sinks, Longest Chain {}",
+      dag.groups.len(), dag.sources.len(), dag.sinks.len(), dag.longest_chain.len()
+    ));
+    let group = dag.group[id];
+    if dag.groups[group].len() > 1 {
+      ui.label(format!("Cycles with {} other bubbles", dag.groups[group].len() - 1));
+    }
+
+    let exit = Self::bubble_list(ui, dag, "Exits into", &dag.exits[id]);
+    let entry = Self::bubble_list(ui, dag, "Entered from", &dag.entries[id]);
+    if let Some(id) = exit.or(entry) {
+      let (bubble_idx, state_idx) = dag.location(id);
+      self.bubble = bubble_idx + 1;
+      self.state = state_idx + 1;
+    }
+  }
+
+  pub fn assign_self(&mut self, program: &mut GraphProgram, state: AnyState) {
     let (bubble_idx, state_idx, state_length) = if let Some(state_space) = &program.state_space {
       let (bubble_idx, state_idx) = state_space.bubble_data(state);
-      let state_len = state_space.bubbles[bubble_idx].len();
+      let state_len = state_space.bubble_len(bubble_idx);
       (bubble_idx, state_idx, state_len)
     } else { (0, 0, 0) };
     self.bubble = bubble_idx + 1;
@@ -30,8 +73,8 @@ impl super::Mode for Bubbles {
     
     let (bubble_idx, bubble_len, state_idx, state_len) = if let Some(state_space) = &program.state_space {
       let (bubble_idx, state_idx) = state_space.bubble_data(program.loaded_state);
-      let state_len = state_space.bubbles[bubble_idx].len();
-      (bubble_idx, state_space.bubbles.len(), state_idx, state_len)
+      let state_len = state_space.bubble_len(bubble_idx);
+      (bubble_idx, state_space.bubble_count(), state_idx, state_len)
     } else { (0, 0, 0, 0) };
     Self {
       bubble: bubble_idx + 1,
@@ -54,12 +97,10 @@ impl super::Mode for Bubbles {
       ui.label(format!("/{} Viewed Bubbles", self.bubble_length));
     });
     if self.bubble != old_bubble_idx { self.state = 1; }
-    self.bubble_length = state_space.bubbles.len();
+    self.bubble_length = state_space.bubble_count();
 
-    let Some(bubble_vec) = state_space.bubbles.get(self.bubble - 1) else {
-      return;
-    };
-    self.state_length = bubble_vec.len();
+    if self.bubble > self.bubble_length { return }
+    self.state_length = state_space.bubble_len(self.bubble - 1);
 
     ui.horizontal(|ui| {
       DragValue::new(&mut self.state)
@@ -68,14 +109,16 @@ impl super::Mode for Bubbles {
       ;
       ui.label(format!("/{} Viewed States", self.state_length));
     });
-    if self.bubble == state_space.bubbles.len() {
+    if self.bubble == state_space.bubble_count() {
       ui.label("Bubble of Size 1 Bubbles");
     }
 
     // Load current viewing state
-    if let Some(state) = bubble_vec.get(self.state - 1) {
-      program.desired_state = *state;
-    }
+    if let Some(state) = state_space.bubble_state(self.bubble - 1, self.state - 1) {
+      program.desired_state = state;
+    } else { return }
+
+    self.draw_dag(program, ui);
   }
 
   fn interactions(&mut self, program: &mut GraphProgram, response: Response) {
@@ -1,19 +1,74 @@
+use std::collections::VecDeque;
+
 use super::common::*;
+use crate::state::AnyState;
+
+// How many frames each move of a solution stays on screen
+const SOLUTION_STEP_FRAMES: usize = 30;
 
 #[derive(Debug)]
 pub struct Play { 
   reversible_only: bool,
+  solution: VecDeque<(usize, i8)>,
+  solution_length: usize,
+  step_cd: usize,
+  unsolvable: bool,
+}
+impl Play {
+  fn solve(&mut self, program: &GraphProgram, target: AnyState) {
+    let Some(state_space) = &program.state_space else { return };
+    let solution = state_space.solve(program.loaded_state, target, self.reversible_only);
+    self.unsolvable = solution.is_none();
+    self.solution = solution.unwrap_or_default().into();
+    self.solution_length = self.solution.len();
+    self.step_cd = 0;
+  }
 }
 impl super::Mode for Play {
 
   fn create(_program: &GraphProgram) -> Self {
     Self {
       reversible_only: true,
+      solution: VecDeque::new(),
+      solution_length: 0,
+      step_cd: 0,
+      unsolvable: false,
     }
   }
 
-  fn ui(&mut self, _program: &mut GraphProgram, ui: &mut Ui) {
+  fn ui(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
     ui.checkbox(&mut self.reversible_only, "Reversible Only");
+
+    ui.horizontal(|ui| {
+      if ui.button("Solve to Zero").clicked() { self.solve(program, AnyState::default()); }
+      if let Some(target) = program.solve_target
+        && ui.button("Solve to Target").clicked()
+      { self.solve(program, target); }
+    });
+
+    if !self.solution.is_empty() {
+      ui.label(format!("Move {}/{}", self.solution_length - self.solution.len(), self.solution_length));
+      ui.ctx().request_repaint();
+    } else if self.unsolvable {
+      ui.label("No Solution");
+    }
+  }
+
+  fn tick(&mut self, program: &mut GraphProgram) {
+    self.step_cd = self.step_cd.saturating_sub(1);
+    if self.step_cd != 0 { return }
+    let Some((center, operation)) = self.solution.pop_front() else { return };
+    if let Some(state_space) = &program.state_space
+      && let Some(state) = state_space.splash_state(
+        program.loaded_state,
+        center,
+        operation,
+        self.reversible_only
+      )
+    {
+      program.move_to(state);
+    }
+    self.step_cd = SOLUTION_STEP_FRAMES;
   }
 
   fn interactions(&mut self, program: &mut GraphProgram, response: Response) {
@@ -32,7 +87,10 @@ impl super::Mode for Play {
         self.reversible_only
       )
     {
-      program.desired_state = state;
+      program.move_to(state);
+      // Whatever we were solving no longer applies
+      self.solution.clear();
+      self.unsolvable = false;
     }
   }
 
@@ -31,11 +31,13 @@ impl super::Mode for Set {
 
       if let Some(pos) = input.pointer.hover_pos()
         && let Some(node) = program.get_node_at(pos)
-        && self.value <= program.max
         && input.pointer.primary_pressed()
         && let Some(state_space) = &program.state_space
+        // Against the node's own max
+        && self.value < state_space.bases()[node]
       {
-        program.desired_state = state_space.set_packed(program.loaded_state, node, self.value);
+        let state = state_space.set_packed(program.loaded_state, node, self.value);
+        program.move_to(state);
       }
     });
     
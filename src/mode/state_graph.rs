@@ -0,0 +1,197 @@
+use ahash::AHashMap;
+use eframe::egui::{Color32, ComboBox, Context, Pos2, Sense, Stroke, Vec2, Window};
+
+use super::analyze::{focused_state, view_types};
+use super::common::*;
+use crate::graph::points_on_circle;
+use crate::state::{AnyState, Classification, StateSpace};
+
+// Past this the circle turns into a smudge
+const MAX_STATE_NODES: usize = 300;
+const STATE_GRAPH_SIZE: f32 = 500.;
+const STATE_NODE_RADIUS: f32 = 6.;
+
+/// Draws a set of states as a second graph, with a node per state and an edge per move between them
+#[derive(Debug)]
+pub struct StateGraph {
+  // 0 is the loaded state's bubble, the rest index into analyze's view types
+  source: usize,
+  show_irreversible: bool,
+  // What the current states were built from, (source, bubble_idx)
+  built_from: Option<(usize, usize)>,
+  // How many states the source had before being cut down to MAX_STATE_NODES
+  source_len: usize,
+  states: Vec<AnyState>,
+  positions: Vec<Pos2>,
+  // (from, to, reversible)
+  edges: Vec<(usize, usize, bool)>,
+}
+impl StateGraph {
+  fn source_states(&mut self, state_space: &dyn StateSpace, loaded_state: AnyState) -> Vec<AnyState> {
+    if self.source == 0 {
+      let (bubble_idx, state_idx) = state_space.bubble_data(loaded_state);
+      // Every state in the last bubble is a bubble of its own
+      if bubble_idx == state_space.bubble_count() - 1 {
+        self.source_len = 1;
+        return vec![state_space.canonical(loaded_state)]
+      }
+      self.source_len = state_space.bubble_len(bubble_idx);
+      // The loaded state always makes the cut, otherwise it'd be rebuilt looking for it every frame
+      let mut shown = (0 .. self.source_len.min(MAX_STATE_NODES)).collect::<Vec<_>>();
+      if state_idx >= MAX_STATE_NODES { *shown.last_mut().unwrap() = state_idx }
+      return shown.into_iter()
+        .filter_map(|idx| state_space.bubble_state(bubble_idx, idx))
+        .collect()
+    }
+
+    let lists = &view_types(state_space)[self.source - 1].1;
+    self.source_len = lists.iter().map(|list| state_space.list_len(*list)).sum();
+    (0 .. self.source_len.min(MAX_STATE_NODES))
+      .filter_map(|idx| focused_state(state_space, lists, idx))
+      .collect()
+  }
+
+  fn rebuild(&mut self, state_space: &dyn StateSpace, loaded_state: AnyState) {
+    self.states = self.source_states(state_space, loaded_state);
+    let center = Pos2::new(STATE_GRAPH_SIZE / 2., STATE_GRAPH_SIZE / 2.);
+    self.positions = points_on_circle(self.states.len(), center, STATE_GRAPH_SIZE / 2. - STATE_NODE_RADIUS);
+
+    let index: AHashMap<AnyState, usize> = self.states.iter().enumerate().map(|(idx, state)| (*state, idx)).collect();
+    self.edges.clear();
+    for (from, state) in self.states.iter().enumerate() {
+      for center in 0 .. state_space.length() {
+        for operation in [1, -1] {
+          let Some(next) = state_space.splash_state(*state, center, operation, false) else { continue };
+          let Some(&to) = index.get(&state_space.canonical(next)) else { continue };
+          if to == from { continue }
+          let reversible = state_space.splash_state(*state, center, operation, true).is_some();
+          // The reverse move draws the same line
+          if reversible && to < from { continue }
+          self.edges.push((from, to, reversible));
+        }
+      }
+    }
+    self.edges.sort_unstable();
+    self.edges.dedup();
+  }
+
+  fn color(classification: Classification) -> Color32 {
+    match classification {
+      Classification::Valid => Color32::DARK_GREEN,
+      Classification::InvalidTheorem(_) => Color32::ORANGE,
+      Classification::InvalidOther => Color32::RED,
+    }
+  }
+
+  fn draw_state_graph(&self, program: &mut GraphProgram, ctx: &Context) {
+    let Some(state_space) = program.state_space.as_ref() else { return };
+    // States stand in for their orbit when exploring orbits
+    let loaded_state = state_space.canonical(program.loaded_state);
+    let mut clicked = None;
+    Window::new("State Graph")
+      .default_pos(Pos2::new(400., 15.))
+      .show(ctx, |ui| {
+        let (response, painter) = ui.allocate_painter(Vec2::splat(STATE_GRAPH_SIZE), Sense::click());
+        let offset = response.rect.min.to_vec2();
+
+        for (from, to, reversible) in &self.edges {
+          if !reversible && !self.show_irreversible { continue }
+          let (start, end) = (self.positions[*from] + offset, self.positions[*to] + offset);
+          if *reversible {
+            painter.line_segment([start, end], Stroke::new(1., Color32::WHITE));
+          } else {
+            // Stop at the edge of the node so the tip stays visible
+            let direction = end - start;
+            let length = direction.length();
+            let arrow = direction * ((length - STATE_NODE_RADIUS) / length);
+            painter.arrow(start, arrow, Stroke::new(1., Color32::GRAY));
+          }
+        }
+
+        for (state, position) in self.states.iter().zip(&self.positions) {
+          let color = Self::color(state_space.classification_data(*state).0);
+          painter.circle_filled(*position + offset, STATE_NODE_RADIUS, color);
+          if *state == loaded_state {
+            painter.circle_stroke(*position + offset, STATE_NODE_RADIUS + 2., Stroke::new(2., Color32::WHITE));
+          }
+        }
+
+        if response.clicked()
+          && let Some(pos) = response.interact_pointer_pos()
+        {
+          clicked = self.positions.iter()
+            .position(|position| (*position + offset).distance(pos) < STATE_NODE_RADIUS * 1.5)
+          ;
+        }
+      });
+
+    if let Some(idx) = clicked { program.desired_state = self.states[idx]; }
+  }
+}
+impl super::Mode for StateGraph {
+
+  fn create(_program: &GraphProgram) -> Self {
+    Self {
+      source: 0,
+      show_irreversible: false,
+      built_from: None,
+      source_len: 0,
+      states: Vec::new(),
+      positions: Vec::new(),
+      edges: Vec::new(),
+    }
+  }
+
+  fn ui(&mut self, program: &mut GraphProgram, ui: &mut Ui) {
+
+    let Some(state_space) = program.state_space.as_ref() else { return };
+
+    let view_types = view_types(state_space.as_ref());
+    let name = |source: usize| if source == 0 { "Current Bubble" } else { &view_types[source - 1].0 };
+    ComboBox::from_label("States").selected_text(name(self.source))
+      .show_ui(ui, |ui| {
+        for source in 0 ..= view_types.len() {
+          ui.selectable_value(&mut self.source, source, name(source));
+        }
+      })
+    ;
+    ui.checkbox(&mut self.show_irreversible, "Irreversible Moves");
+
+    // Only the bubble source changes with the loaded state
+    let bubble_idx = if self.source == 0 { state_space.bubble_data(program.loaded_state).0 } else { 0 };
+    let in_states = self.states.contains(&state_space.canonical(program.loaded_state));
+    if self.built_from != Some((self.source, bubble_idx)) || (self.source == 0 && !in_states) {
+      self.rebuild(state_space.as_ref(), program.loaded_state);
+      self.built_from = Some((self.source, bubble_idx));
+    }
+
+    if self.source_len > self.states.len() {
+      ui.label(format!("Showing {} of {} States", self.states.len(), self.source_len));
+    } else {
+      ui.label(format!("{} States, {} Moves", self.states.len(), self.edges.len()));
+    }
+
+    self.draw_state_graph(program, ui.ctx());
+  }
+
+  fn interactions(&mut self, program: &mut GraphProgram, response: Response) {
+
+    // Moves on the main graph walk around the state graph
+    response.ctx.input(|input| {
+      let delta =
+        if input.pointer.primary_pressed() { 1 }
+        else if input.pointer.secondary_pressed() { -1 }
+        else { return } as i8
+      ;
+      if   let Some(pos) = input.pointer.interact_pos()
+        && let Some(node) = program.get_node_at(pos)
+        && let Some(state_space) = &program.state_space
+        && let Some(state) = state_space.splash_state(program.loaded_state, node, delta, false)
+      {
+        program.desired_state = state;
+      }
+    });
+
+  }
+
+}
@@ -1,119 +1,371 @@
-use ahash::{AHashMap, AHashSet};
+mod cache;
+mod dag;
+mod dense;
+mod modular;
+mod packed;
+mod parallel;
+mod product;
+mod progress;
+mod rule;
+mod swar;
+mod symmetry;
+mod theorem;
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::mem::take;
 
-use crate::graph::Graph;
+use ahash::{AHashMap, AHashSet};
 
-pub type PackedState = u128;
-#[derive(Clone, Copy)]
-#[repr(usize)]
+pub use cache::Cache;
+pub use dag::BubbleDag;
+use dense::{Bitset, DenseMeta, Marks};
+pub use modular::ModularSolver;
+pub use packed::{AnyState, PackedState};
+pub use product::ProductData;
+pub use progress::{Exploration, Phase, Progress};
+pub use rule::{Boundary, MoveRule, Neighborhood};
+pub use theorem::Theorem;
+use progress::REPORT_INTERVAL;
+use swar::Swar;
+use symmetry::Symmetry;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Classification {
   Valid,
-  InvalidT1,
+  /// Proven invalid by the theorem at this index, see [StateData::theorems]
+  InvalidTheorem(usize),
   InvalidOther,
 }
 
-struct Metadata {
-  classification: Option<(Classification, usize)>,
-  bubble: Option<(usize, usize)>,
+/// Explores the state space of a contiguized graph's neighbors,
+/// packing states into the smallest backend that fits.
+/// Disconnected graphs are explored one component at a time when they can be, see [ProductData].
+/// With `orbits` only one state per orbit of the graph's automorphisms gets explored, see [StateData::canonical].
+/// `maxes` has each node's max, see [crate::graph::Graph::get_maxes].
+/// With a `cache` the state space is read from it when it's been explored before, and saved to it otherwise.
+/// `threads` is passed on to [StateData::new].
+/// Returns None if the graph is empty, has too many states to index, or `progress` gets cancelled
+#[allow(clippy::too_many_arguments)]
+pub fn explore(
+  neighbors: Vec< Vec<usize> >, maxes: Vec<u8>, rule: MoveRule, encoding: Encoding, orbits: bool, cache: Option<&Cache>,
+  threads: Option<usize>, progress: &Progress,
+) -> Option< Box<dyn StateSpace> > {
+  let bits = StateOps::new(maxes.iter().map(|max| max + 1).collect(), encoding).bits();
+  if bits > <AnyState as PackedState>::BITS { return None }
+  if bits <= <u64 as PackedState>::BITS {
+    explore_packed::<u64>(neighbors, maxes, rule, encoding, orbits, cache, threads, progress)
+  } else if bits <= <u128 as PackedState>::BITS {
+    explore_packed::<u128>(neighbors, maxes, rule, encoding, orbits, cache, threads, progress)
+  } else { explore_packed::<AnyState>(neighbors, maxes, rule, encoding, orbits, cache, threads, progress) }
 }
-impl Metadata {
-  fn set_bubble(&mut self, bubble_idx: usize, state_idx: usize) { self.bubble = Some((bubble_idx, state_idx)); }
-  fn classification(&self) -> (Classification, usize) { self.classification.unwrap() }
-  fn bubble(&self) -> (usize, usize) { self.bubble.unwrap() }
+
+#[allow(clippy::too_many_arguments)]
+fn explore_packed<S: PackedState>(
+  neighbors: Vec< Vec<usize> >, maxes: Vec<u8>, rule: MoveRule, encoding: Encoding, orbits: bool, cache: Option<&Cache>,
+  threads: Option<usize>, progress: &Progress,
+) -> Option< Box<dyn StateSpace> > {
+  // Automorphisms can swap whole components, so orbits don't factor.
+  // Each component gets cached on its own
+  if !orbits && let Some(components) = ProductData::<S>::factors(&neighbors, &maxes, rule) {
+    return Some(Box::new(ProductData::<S>::new(neighbors, components, maxes, rule, encoding, cache, threads, progress)?))
+  }
+  let Some(cache) = cache else {
+    return Some(Box::new(StateData::<S>::new(neighbors, maxes, rule, encoding, orbits, threads, progress)?))
+  };
+  if let Some(data) = cache.load::<S>(&neighbors, &maxes, rule, encoding, orbits) { return Some(Box::new(data)) }
+  let data = StateData::<S>::new(neighbors, maxes, rule, encoding, orbits, threads, progress)?;
+  cache.store(&data, orbits);
+  Some(Box::new(data))
 }
 
-pub struct StateData {
-  meta: AHashMap<PackedState, Metadata>,
-  pub bubbles: Vec< Vec<PackedState> >,
-  states: [Vec<PackedState>; 3], // One vec per Classification
+pub struct StateData<S: PackedState> {
+  meta: DenseMeta,
+  pub bubbles: Vec< Vec<S> >,
+  pub dag: BubbleDag,
+  states: Vec< Vec<S> >, // One vec per Classification, see Self::list_idx
+  theorems: Vec< Box<dyn Theorem<S>> >,
+  // One more than the largest node's max
   pub base: u8,
-  neighbors: Vec< Vec<usize> >
+  ops: StateOps,
+  pub rule: MoveRule,
+  neighbors: Vec< Vec<usize> >,
+  // Only set when exploring orbits
+  symmetry: Option<Symmetry>,
+  // Only set when wrapping
+  solver: Option<ModularSolver>,
+  // Unset when the state has no spare bit, see Swar
+  swar: Option< Swar<S> >,
+  // 1 takes the serial path, see parallel::threads
+  threads: usize,
 }
-impl StateData {
-  pub fn new(graph: &mut Graph, max: u8) -> Option<Self> {
-    graph.contiguize_and_trim();
+impl<S: PackedState> StateData<S> {
+  /// Expects the neighbors of a contiguized graph, see [crate::graph::Graph::get_neighbors].
+  /// With `orbits` every list and bubble only holds canonical states, see [Self::canonical].
+  /// `threads` sets how many threads explore, None picks by the number of states, see parallel::threads.
+  /// None if the graph is empty, has too many states to index, or `progress` gets cancelled
+  pub fn new(
+    neighbors: Vec< Vec<usize> >, maxes: Vec<u8>, rule: MoveRule, encoding: Encoding, orbits: bool,
+    threads: Option<usize>, progress: &Progress,
+  ) -> Option<Self> {
+    let mut data = Self::unexplored(neighbors, maxes, rule, encoding, orbits)?;
+    if let Some(threads) = threads { data.threads = threads.max(1) }
+    let total = data.meta.len();
+
+    progress.start(Phase::GenerateValid, 0);
+    let valid = data.generate_valid(progress);
+    if progress.is_cancelled() { return None }
+
+    progress.start(Phase::GenerateInvalid, total as u64);
+    let invalid = data.generate_invalid(&valid, progress);
+    if progress.is_cancelled() { return None }
+
+    progress.start(Phase::ClassifyInvalid, invalid.len() as u64);
+    data.classify_invalid(invalid, progress);
+    if progress.is_cancelled() { return None }
+
+    // Fewer than total when only exploring orbits
+    let tracked = data.states.iter().map(Vec::len).sum::<usize>() as u64;
+    // The parallel search goes over every state twice, see Self::identify_bubbles_parallel
+    let passes = if data.threads > 1 { 2 } else { 1 };
+    progress.start(Phase::IdentifyBubbles, tracked * passes);
+    data.identify_bubbles(progress);
+    if progress.is_cancelled() { return None }
+
+    progress.start(Phase::CondenseBubbles, tracked);
+    data.condense_bubbles(progress);
+    if progress.is_cancelled() { return None }
+
+    Some(data)
+  }
 
-    let neighbors = graph.get_neighbors();
+  // Everything but the lists, bubbles and DAG, which Self::new explores and a Cache reads back
+  fn unexplored(
+    neighbors: Vec< Vec<usize> >, maxes: Vec<u8>, rule: MoveRule, encoding: Encoding, orbits: bool
+  ) -> Option<Self> {
     if neighbors.is_empty() { return None; }
 
-    let length = neighbors.len();
-    let base = max + 1;
-    assert!(length * StateOps::bits_per_digit(base) <= 128);
+    let bases = maxes.iter().map(|max| max + 1).collect::<Vec<_>>();
+    let ops = StateOps::new(bases.clone(), encoding);
+    if ops.bits() > S::BITS { return None }
+    let base = *bases.iter().max().unwrap();
+    let symmetry = if orbits { Symmetry::new(&neighbors, ops.clone()) } else { None };
+    let solver = (rule.boundary == Boundary::Wrap).then(|| ModularSolver::new(&neighbors, &bases, rule));
+    let total = ops.total()?;
+    let swar = Swar::new(&neighbors, &ops, rule);
 
     let mut data = Self {
-      meta: AHashMap::new(),
+      meta: DenseMeta::new(total)?,
       bubbles: Vec::new(),
-      states: [Vec::new(), Vec::new(), Vec::new()],
+      dag: BubbleDag::new(0, Vec::new()),
+      states: Vec::new(),
+      theorems: theorem::registered(rule),
       base,
-      neighbors: graph.get_neighbors(),
+      ops,
+      rule,
+      neighbors,
+      symmetry,
+      solver,
+      swar,
+      threads: parallel::threads(total),
     };
+    data.states = (0 .. data.theorems.len() + 2).map(|_| Vec::new()).collect();
+    Some(data)
+  }
 
-    data.generate_valid();
-    let invalid = data.generate_invalid();
-    data.classify_invalid(invalid);
-    
-    data.identify_bubbles();
+  pub fn get_list(&self, classification: Classification) -> &Vec<S> {
+    &self.states[self.list_idx(classification)]
+  }
 
-    Some(data)
+  fn list_idx(&self, classification: Classification) -> usize {
+    match classification {
+      Classification::Valid => 0,
+      Classification::InvalidTheorem(theorem) => theorem + 1,
+      Classification::InvalidOther => self.theorems.len() + 1,
+    }
   }
 
-  pub fn get_list(&self, classification: Classification) -> &Vec<PackedState> {
-    &self.states[classification as usize]
+  // Inverse of Self::list_idx
+  fn list_classification(&self, list_idx: usize) -> Classification {
+    match list_idx {
+      0 => Classification::Valid,
+      list_idx if list_idx <= self.theorems.len() => Classification::InvalidTheorem(list_idx - 1),
+      _ => Classification::InvalidOther,
+    }
   }
 
-  fn track_unique_state(&mut self, state: PackedState, classification: Classification) -> bool {
-    if self.meta.contains_key(&state) { return false; }
-    let state_vec = &mut self.states[classification as usize];
-    let metadata = Metadata {
-      classification: Some((classification, state_vec.len())),
-      bubble: None
-    };
-    self.meta.insert(state.clone(), metadata);
+  pub fn theorems(&self) -> &[Box<dyn Theorem<S>>] { &self.theorems }
+
+  fn track_unique_state(&mut self, state: S, classification: Classification) -> bool {
+    let rank = self.ops.rank(state);
+    if self.meta.is_tracked(rank) { return false; }
+    let list_idx = self.list_idx(classification);
+    let state_vec = &mut self.states[list_idx];
+    self.meta.set_classification(rank, list_idx, state_vec.len());
     state_vec.push(state);
     true
   }
 
   pub fn length(&self) -> usize { self.neighbors.len() }
 
-  pub fn parse_state(&self, state: PackedState) -> Vec<u8> {
-    StateOps::to_vec(state, self.base, self.length())
+  pub fn parse_state(&self, state: S) -> Vec<u8> {
+    self.ops.unpack(state)
   }
 
-  pub fn parse_vec(&self, vec: Vec<u8>) -> PackedState {
-    StateOps::from_vec(vec, self.base)
+  pub fn parse_vec(&self, vec: Vec<u8>) -> S {
+    self.ops.pack(vec)
   }
 
-  pub fn set_packed(&self, state: PackedState, idx: usize, value: u8) -> PackedState {
-    StateOps::set(state, idx, value, self.base, self.length())
+  pub fn set_packed(&self, state: S, idx: usize, value: u8) -> S {
+    self.ops.set(state, idx, value)
   }
 
-  pub fn classification_data(&self, state: PackedState) -> (Classification, usize) {
-    self.meta.get(&state).unwrap().classification()
+  pub fn classification_data(&self, state: S) -> (Classification, usize) {
+    let (list_idx, idx) = self.meta.classification(self.ops.rank(self.canonical(state))).unwrap();
+    (self.list_classification(list_idx), idx)
   }
 
   /// Returns (bubble_idx, state_idx)
-  pub fn bubble_data(&self, state: PackedState) -> (usize, usize) {
-    self.meta.get(&state).unwrap().bubble()
+  pub fn bubble_data(&self, state: S) -> (usize, usize) {
+    self.meta.bubble(self.ops.rank(self.canonical(state)))
+  }
+
+  /// The smallest state in the orbit of `state` under the graph's automorphisms when exploring orbits,
+  /// otherwise `state` itself
+  pub fn canonical(&self, state: S) -> S {
+    self.symmetry.as_ref().map_or(state, |symmetry| symmetry.canonical(state))
+  }
+
+  /// How many states `state` stands in for, None when not exploring orbits
+  pub fn orbit_size(&self, state: S) -> Option<usize> {
+    Some(self.symmetry.as_ref()?.orbit(state).len())
   }
 
+  /// Only set when wrapping, see [Boundary::Wrap]
+  pub fn solver(&self) -> Option<&ModularSolver> { self.solver.as_ref() }
+
+}
+/// Type erased view of a [StateData], states are passed around as [AnyState]
+pub trait StateSpace: Send {
+  /// One more than the largest max, see [Self::bases]
+  fn base(&self) -> u8;
+  /// One more than each node's max
+  fn bases(&self) -> &[u8];
+  fn rule(&self) -> MoveRule;
+  fn length(&self) -> usize;
+
+  fn parse_state(&self, state: AnyState) -> Vec<u8>;
+  fn parse_vec(&self, vec: Vec<u8>) -> AnyState;
+  fn set_packed(&self, state: AnyState, idx: usize, value: u8) -> AnyState;
+  fn splash_state(&self, state: AnyState, center: usize, operation: i8, reversible_only: bool) -> Option<AnyState>;
+  fn neighborhood_zero_or_max(&self, state: AnyState, node: usize) -> (bool, bool);
+  /// See [StateData::solve]
+  fn solve(&self, from: AnyState, to: AnyState, reversible_only: bool) -> Option< Vec<(usize, i8)> >;
+
+  fn classification_data(&self, state: AnyState) -> (Classification, usize);
+  fn theorem_names(&self) -> Vec<&str>;
+  /// Whether the theorem at this index holds, whatever the state's classification is
+  fn theorem_holds(&self, theorem: usize, state: AnyState) -> bool;
+  fn list_len(&self, classification: Classification) -> usize;
+  fn list_state(&self, classification: Classification, idx: usize) -> Option<AnyState>;
+  /// See [frequency_analysis], states are taken from each list in order
+  fn frequency_analysis(&self, classifications: &[Classification]) -> Vec< Vec<u32> >;
+
+  /// Returns (bubble_idx, state_idx)
+  fn bubble_data(&self, state: AnyState) -> (usize, usize);
+  fn bubble_count(&self) -> usize;
+  fn bubble_len(&self, bubble: usize) -> usize;
+  fn bubble_state(&self, bubble: usize, idx: usize) -> Option<AnyState>;
+  fn bubble_dag(&self) -> &BubbleDag;
+
+  /// See [StateData::canonical], lists and bubbles only hold canonical states
+  fn canonical(&self, state: AnyState) -> AnyState;
+  /// See [StateData::orbit_size]
+  fn orbit_size(&self, state: AnyState) -> Option<usize>;
 }
-impl StateData {
-  fn generate_valid(&mut self) {
-    for state in self.dfs(0, false) {
-      self.track_unique_state(state, Classification::Valid);
+impl<S: PackedState> StateSpace for StateData<S> {
+  fn base(&self) -> u8 { self.base }
+  fn bases(&self) -> &[u8] { &self.ops.bases }
+  fn rule(&self) -> MoveRule { self.rule }
+  fn length(&self) -> usize { self.length() }
+
+  fn parse_state(&self, state: AnyState) -> Vec<u8> { self.parse_state(S::narrow(state)) }
+  fn parse_vec(&self, vec: Vec<u8>) -> AnyState { self.parse_vec(vec).widen() }
+  fn set_packed(&self, state: AnyState, idx: usize, value: u8) -> AnyState {
+    self.set_packed(S::narrow(state), idx, value).widen()
+  }
+  fn splash_state(&self, state: AnyState, center: usize, operation: i8, reversible_only: bool) -> Option<AnyState> {
+    self.splash_state(S::narrow(state), center, operation, reversible_only).map(S::widen)
+  }
+  fn neighborhood_zero_or_max(&self, state: AnyState, node: usize) -> (bool, bool) {
+    self.neighborhood_zero_or_max(S::narrow(state), node)
+  }
+  fn solve(&self, from: AnyState, to: AnyState, reversible_only: bool) -> Option< Vec<(usize, i8)> > {
+    self.solve(S::narrow(from), S::narrow(to), reversible_only)
+  }
+
+  fn classification_data(&self, state: AnyState) -> (Classification, usize) {
+    self.classification_data(S::narrow(state))
+  }
+  fn theorem_names(&self) -> Vec<&str> { self.theorems().iter().map(|theorem| theorem.name()).collect() }
+  fn theorem_holds(&self, theorem: usize, state: AnyState) -> bool {
+    self.theorems[theorem].holds(self, S::narrow(state))
+  }
+  fn list_len(&self, classification: Classification) -> usize { self.get_list(classification).len() }
+  fn list_state(&self, classification: Classification, idx: usize) -> Option<AnyState> {
+    self.get_list(classification).get(idx).copied().map(S::widen)
+  }
+  fn frequency_analysis(&self, classifications: &[Classification]) -> Vec< Vec<u32> > {
+    let mut result = Vec::new();
+    for classification in classifications {
+      result.extend(frequency_analysis(self.get_list(*classification), &self.ops, self.base - 1));
     }
+    result
   }
 
-  // Compute the not of the valid set
-  fn generate_invalid(&self) -> Vec<PackedState> {
-    if self.states.is_empty() { return Vec::new() }
+  fn bubble_data(&self, state: AnyState) -> (usize, usize) { self.bubble_data(S::narrow(state)) }
+  fn bubble_count(&self) -> usize { self.bubbles.len() }
+  fn bubble_len(&self, bubble: usize) -> usize { self.bubbles[bubble].len() }
+  fn bubble_state(&self, bubble: usize, idx: usize) -> Option<AnyState> {
+    self.bubbles.get(bubble)?.get(idx).copied().map(S::widen)
+  }
+  fn bubble_dag(&self) -> &BubbleDag { &self.dag }
+
+  fn canonical(&self, state: AnyState) -> AnyState { self.canonical(S::narrow(state)).widen() }
+  fn orbit_size(&self, state: AnyState) -> Option<usize> { self.orbit_size(S::narrow(state)) }
+}
+impl<S: PackedState> StateData<S> {
+  // Marks the rank of every valid state, they get tracked by Self::generate_invalid
+  fn generate_valid(&self, progress: &Progress) -> Bitset {
+    if self.threads > 1 { return self.generate_valid_parallel(progress) }
+    let mut valid = Bitset::new(self.meta.len());
+    self.dfs(S::default(), false, &mut valid, progress, |_| ());
+    valid
+  }
+
+  // Tracks the valid states and computes the not of the valid set, both in rank order
+  fn generate_invalid(&mut self, valid: &Bitset, progress: &Progress) -> Vec<S> {
+    if self.threads > 1 { return self.generate_invalid_parallel(valid, progress) }
     let mut missing = Vec::new();
     
     // We know the zero state is always valid, which is why we're allowed to increment immediately
-    let mut cur_state = 0;
-    while let Some(state) = StateOps::increment(cur_state, self.base, self.length()) {
+    self.track_unique_state(S::default(), Classification::Valid);
+    let mut cur_state = S::default();
+    let mut rank = 0;
+    let mut unreported = 1;
+    while let Some(state) = self.ops.increment(cur_state) {
       cur_state = state;
-      if !self.meta.contains_key(&cur_state) { missing.push(cur_state); }
+      rank += 1;
+      debug_assert_eq!(rank, self.ops.rank(cur_state));
+      if valid.contains(rank) { self.track_unique_state(cur_state, Classification::Valid); }
+      else if self.is_canonical(cur_state) { missing.push(cur_state); }
+      unreported += 1;
+      if unreported == REPORT_INTERVAL {
+        progress.advance(take(&mut unreported));
+        if progress.is_cancelled() { break }
+      }
     }
+    progress.advance(unreported);
   
     missing
   }
@@ -122,117 +374,284 @@ impl StateData {
   // But the entire point of this research is to see if p = np, whether we can trivially confirm
   // whether a state is valid or invalid.
   // Right now, we can only define the set of invalid states as the not of the valid set.
-  fn classify_invalid(&mut self, invalid_list: Vec<PackedState>) {
+  fn classify_invalid(&mut self, invalid_list: Vec<S>, progress: &Progress) {
     if invalid_list.is_empty() { return; }
-    for state in invalid_list {
-      if self.is_invalid_theorem_1(state) {
-        self.track_unique_state(state, Classification::InvalidT1);
-      } else {
-        self.track_unique_state(state, Classification::InvalidOther);
+    if self.threads > 1 { return self.classify_invalid_parallel(invalid_list, progress) }
+    for chunk in invalid_list.chunks(REPORT_INTERVAL as usize) {
+      for state in chunk {
+        self.track_unique_state(*state, self.classify(*state));
       }
+      progress.advance(chunk.len() as u64);
+      if progress.is_cancelled() { return }
     }
   }
 
-  // If every node's closed neighborhood contains a min and a max,
-  // this state is a theorem one invalid
-  fn is_invalid_theorem_1(&self, state: PackedState) -> bool {
-    for center in 0 .. self.length() {
-      let (has_zero, has_max) = self.neighborhood_zero_or_max(state, center);
-      if !(has_zero && has_max) { return false }
-    }
-    return true;
+  // The first theorem that proves an invalid state invalid
+  fn classify(&self, state: S) -> Classification {
+    self.theorems.iter()
+      .position(|theorem| theorem.holds(self, state))
+      .map_or(Classification::InvalidOther, Classification::InvalidTheorem)
   }
 
-  pub fn neighborhood_zero_or_max(&self, state: PackedState, node: usize) -> (bool, bool) {
-    let mut has_zero = false;
-    let mut has_max = false;
-
-    for node in self.neighbors[node].iter().chain(&[node])
-      .map( |idx| { StateOps::get(state, *idx, self.base, self.length()) } )
-    {
-      has_zero |= node == 0;
-      has_max |= node == self.base - 1;
-      if has_zero && has_max { break }
-    }
-
-    (has_zero, has_max)
+  pub fn neighborhood_zero_or_max(&self, state: S, node: usize) -> (bool, bool) {
+    neighborhood_zero_or_max(&self.neighbors, &self.ops, &self.rule, state, node)
   }
 
-  fn identify_bubbles(&mut self) {
-    let mut seen_states = AHashSet::<PackedState>::new();
+  // Reversible moves can always be undone, so the states one reaches are exactly its bubble
+  // and every search starts from a state no other search found
+  fn identify_bubbles(&mut self, progress: &Progress) {
+    if self.threads > 1 { return self.identify_bubbles_parallel(progress) }
+    let mut seen_states = Bitset::new(self.meta.len());
 
     let mut smol_bubbles = Vec::new();
 
-    for initial_state in self.meta.keys() {
-      if seen_states.contains(initial_state) { continue }
-      let mut bubble = self.dfs(*initial_state, true);
+    for initial_state in self.states.iter().flatten() {
+      if seen_states.contains(self.ops.rank(*initial_state)) { continue }
+      let mut bubble = Vec::new();
+      self.dfs(*initial_state, true, &mut seen_states, progress, |state| bubble.push(state));
+      if progress.is_cancelled() { return }
       if bubble.len() == 1 { 
-        smol_bubbles.extend(bubble.drain());
+        smol_bubbles.extend(bubble);
         continue;
       }
-      let mut bubble_vec = Vec::with_capacity(bubble.len());
-      for state in bubble {
-        seen_states.insert(state);
-        bubble_vec.push(state);
-      }
-      self.bubbles.push(bubble_vec);
+      self.bubbles.push(bubble);
     }
+    self.index_bubbles(smol_bubbles);
+  }
 
+  // Points every state at its place in Self::bubbles, then adds the single state bubbles as one last bubble
+  fn index_bubbles(&mut self, smol_bubbles: Vec<S>) {
     for (bubble_vec, bubble) in self.bubbles.iter().enumerate() {
       for (bubble_idx, state) in bubble.iter().enumerate() {
-        self.meta.get_mut(state).unwrap().set_bubble(bubble_vec, bubble_idx);
+        self.meta.set_bubble(self.ops.rank(*state), bubble_vec, bubble_idx);
       }
     }
 
     let single_bubbles = self.bubbles.len();
     for (idx, state) in smol_bubbles.iter().enumerate() {
-      self.meta.get_mut(state).unwrap().set_bubble(single_bubbles, idx);
+      self.meta.set_bubble(self.ops.rank(*state), single_bubbles, idx);
     }
     self.bubbles.push(smol_bubbles);
   
   }
 
-  fn dfs(&self, initial_state: PackedState, reversible_only: bool) -> AHashSet<PackedState> {
+  fn condense_bubbles(&mut self, progress: &Progress) {
+    let big_bubbles = self.bubbles.len() - 1;
+    let id = |(bubble_idx, state_idx): (usize, usize)| {
+      if bubble_idx < big_bubbles { bubble_idx } else { big_bubbles + state_idx }
+    };
+
+    // Ids are handed out in the same order we walk through the bubbles
+    let mut exits = Vec::with_capacity(big_bubbles + self.bubbles[big_bubbles].len());
+    let mut unreported = 0;
+    for (bubble_idx, bubble) in self.bubbles.iter().enumerate() {
+      let mut bubble_exits = AHashSet::new();
+      for (state_idx, state) in bubble.iter().enumerate() {
+        let from = id((bubble_idx, state_idx));
+        for center in 0 .. self.length() {
+          for operation in [-1, 1] {
+            // Rejected moves go nowhere
+            let Some(new_state) = self.splash_state(*state, center, operation, false) else { continue };
+            let to = id(self.bubble_data(new_state));
+            if to != from { bubble_exits.insert(to); }
+          }
+        }
+        if bubble_idx == big_bubbles { exits.push(sorted(take(&mut bubble_exits))) }
+
+        unreported += 1;
+        if unreported == REPORT_INTERVAL {
+          progress.advance(take(&mut unreported));
+          if progress.is_cancelled() { return }
+        }
+      }
+      if bubble_idx < big_bubbles { exits.push(sorted(bubble_exits)) }
+    }
+    progress.advance(unreported);
+
+    self.dag = BubbleDag::new(big_bubbles, exits);
+  }
+
+  fn is_canonical(&self, state: S) -> bool {
+    self.symmetry.as_ref().is_none_or(|symmetry| symmetry.is_canonical(state))
+  }
+
+  /// Visits every state found that isn't in `seen` yet, `initial_state` included, and adds it to `seen`.
+  /// Advances `progress` by every state found, stops early if it gets cancelled.
+  /// Only finds canonical states, see [Self::canonical]
+  fn dfs(&self, initial_state: S, reversible_only: bool, seen: &mut impl Marks, progress: &Progress, mut visit: impl FnMut(S)) {
     let count = self.neighbors.len();
-    let mut stack = vec![(initial_state, 0u8)];
-    let mut found_states = AHashSet::new();
-    found_states.insert(initial_state);
+    let mut stack = vec![(initial_state, 0usize)];
+    seen.mark(self.ops.rank(initial_state));
+    visit(initial_state);
+    let mut unreported = 1;
     
     'search: while let Some((state, op_idx)) = stack.pop() {
-      if (op_idx + 1) >> 1 < count as u8 { stack.push((state.clone(), op_idx + 1)) }
-      let center_idx = (op_idx >> 1) as usize;
+      if unreported >= REPORT_INTERVAL {
+        progress.advance(take(&mut unreported));
+        if progress.is_cancelled() { break }
+      }
+      if (op_idx + 1) >> 1 < count { stack.push((state, op_idx + 1)) }
+      let center_idx = op_idx >> 1;
       // We want to apply a value of -1 if op_idx & 1 == 0 and 1 if op_idx & 1 == 1
       let operation = -1 + (op_idx & 0b1) as i8 * 2;
       
       if let Some(new_state) = self.splash_state(state, center_idx, operation, reversible_only) {
-        if found_states.insert(new_state.clone()) { stack.push((new_state, 0)) }
+        let new_state = self.canonical(new_state);
+        if seen.mark(self.ops.rank(new_state)) { stack.push((new_state, 0)); visit(new_state); unreported += 1; }
       } else { continue 'search }
 
     }
+    progress.advance(unreported);
+  }
 
-    found_states
+  /// Shortest list of (center, operation) moves taking `from` to `to`, None if `to` can't be reached.
+  /// When wrapping the moves come from the [ModularSolver] instead, which isn't always shortest
+  pub fn solve(&self, from: S, to: S, reversible_only: bool) -> Option< Vec<(usize, i8)> > {
+    if let Some(solver) = &self.solver { return self.solve_modular(solver, from, to) }
+
+    // state -> (previous state, center, operation)
+    let mut parents = AHashMap::new();
+    let mut queue = VecDeque::from([from]);
+
+    'search: while let Some(state) = queue.pop_front() {
+      if state == to { break 'search }
+      for center in 0 .. self.length() {
+        for operation in [-1, 1] {
+          let Some(new_state) = self.splash_state(state, center, operation, reversible_only) else { continue };
+          if new_state == from || parents.contains_key(&new_state) { continue }
+          parents.insert(new_state, (state, center, operation));
+          queue.push_back(new_state);
+        }
+      }
+    }
+
+    if from != to && !parents.contains_key(&to) { return None }
+    let mut moves = Vec::new();
+    let mut state = to;
+    while let Some(&(previous, center, operation)) = parents.get(&state) {
+      moves.push((center, operation));
+      state = previous;
+    }
+    moves.reverse();
+    Some(moves)
   }
   
+  // Presses past half the modulus are shorter as -1 moves
+  fn solve_modular(&self, solver: &ModularSolver, from: S, to: S) -> Option< Vec<(usize, i8)> > {
+    let difference = self.parse_state(from).into_iter().zip(self.parse_state(to)).zip(&self.ops.bases)
+      .map(|((from, to), base)| (to + base - from) % base)
+      .collect::<Vec<_>>()
+    ;
+    let modulus = solver.modulus();
+    let mut moves = Vec::new();
+    for (center, presses) in solver.presses(&difference)?.into_iter().enumerate() {
+      if presses <= modulus / 2 { moves.extend((0 .. presses).map(|_| (center, 1))) }
+      else { moves.extend((presses .. modulus).map(|_| (center, -1))) }
+    }
+    Some(moves)
+  }
+
   pub fn splash_state(
     &self,
-    mut state: PackedState,
+    state: S,
     center: usize,
     operation: i8,
     reversible_only: bool,
-  ) -> Option<PackedState> {
-    for idx in self.neighbors[center].iter().chain(&[center]) {
-      let old_node = StateOps::get(state, *idx, self.base, self.length());
-      let new_node = old_node.saturating_add_signed(operation).min(self.base - 1);
-      if old_node == new_node && reversible_only { return None }
-      state = StateOps::set(state, *idx, new_node, self.base, self.length());
-    }
-    Some(state)
+  ) -> Option<S> {
+    if let Some(swar) = &self.swar { return swar.splash(state, center, operation, reversible_only) }
+    splash_state(&self.neighbors, &self.ops, &self.rule, state, center, operation, reversible_only)
+  }
+
+}
+
+// Shared with ProductData, which doesn't have a StateData for the whole graph.
+// Only looks at the nodes a move on `node` touches, see [MoveRule::affected]
+fn neighborhood_zero_or_max<S: PackedState>(
+  neighbors: &[Vec<usize>], ops: &StateOps, rule: &MoveRule, state: S, node: usize
+) -> (bool, bool) {
+  let mut has_zero = false;
+  let mut has_max = false;
+
+  for node in rule.affected(neighbors, node)
+    .map( |(idx, _)| { (ops.get(state, idx), ops.base(idx)) } )
+  {
+    // Against the node's own max
+    let (node, base) = node;
+    has_zero |= node == 0;
+    has_max |= node == base - 1;
+    if has_zero && has_max { break }
   }
 
+  (has_zero, has_max)
+}
+
+fn splash_state<S: PackedState>(
+  neighbors: &[Vec<usize>],
+  ops: &StateOps,
+  rule: &MoveRule,
+  mut state: S,
+  center: usize,
+  operation: i8,
+  reversible_only: bool,
+) -> Option<S> {
+  for (idx, delta) in rule.affected(neighbors, center) {
+    let old_node = ops.get(state, idx);
+    let (new_node, reversible) = rule.apply(old_node, delta, operation, ops.base(idx))?;
+    if !reversible && reversible_only { return None }
+    state = ops.set(state, idx, new_node);
+  }
+  Some(state)
 }
 
-struct StateOps;
+/// How [StateOps] packs a state's digits into a [PackedState]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub enum Encoding {
+  /// Each digit gets a power of two worth of values, so base 3 takes 2 bits,
+  /// but digits are just shifts and masks
+  #[default]
+  BitPacked,
+  /// The state is its own rank, a mixed-radix number with a digit per node, so no bits go to waste.
+  /// Digits take a division, and it falls back to bit packing past 128 bits
+  MixedRadix,
+}
+impl Encoding {
+  pub const ALL: [Self; 2] = [Self::BitPacked, Self::MixedRadix];
+}
+impl Display for Encoding {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::BitPacked => write!(f, "Bit Packed"),
+      Self::MixedRadix => write!(f, "Mixed Radix"),
+    }
+  }
+}
+
+/// Packs one digit per node, see [Encoding]
+#[derive(Clone, Debug)]
+struct StateOps {
+  encoding: Encoding,
+  bases: Vec<u8>,
+  // Bit each digit starts at, with the total bits at the end
+  offsets: Vec<usize>,
+  // Place value of each digit in a state's rank, see Self::rank
+  powers: Vec<u128>,
+  // None if the states don't fit in a u128
+  total: Option<u128>,
+}
 impl StateOps {
+  fn new(bases: Vec<u8>, encoding: Encoding) -> Self {
+    let mut offsets = Vec::with_capacity(bases.len() + 1);
+    offsets.push(0);
+    for base in &bases { offsets.push(offsets.last().unwrap() + Self::bits_per_digit(*base)) }
+    let mut powers = Vec::with_capacity(bases.len());
+    let mut total = Some(1u128);
+    for base in &bases {
+      powers.push(total.unwrap_or(0));
+      total = total.and_then(|total| total.checked_mul(*base as u128));
+    }
+    let encoding = if total.is_some() { encoding } else { Encoding::BitPacked };
+    Self { encoding, bases, offsets, powers, total }
+  }
  
   const fn bits_per_digit(base: u8) -> usize {
     let mut bits = 0;
@@ -244,78 +663,112 @@ impl StateOps {
     bits
   }
 
-  const fn digit_mask(base: u8) -> PackedState {
-    (1u128 << Self::bits_per_digit(base)) - 1
+  fn bits(&self) -> usize {
+    match (self.encoding, self.total) {
+      // Enough for the largest state, total - 1
+      (Encoding::MixedRadix, Some(total)) => (u128::BITS - (total - 1).leading_zeros()) as usize,
+      _ => *self.offsets.last().unwrap(),
+    }
   }
 
-  pub fn get(state: PackedState, idx: usize, base: u8, length: usize) -> u8 {
-    debug_assert!(idx < length);
-    let shift = idx * Self::bits_per_digit(base);
-    ((state >> shift) & Self::digit_mask(base)) as u8
+  fn length(&self) -> usize { self.bases.len() }
+
+  fn base(&self, idx: usize) -> u8 { self.bases[idx] }
+
+  /// How many states there are, None if that doesn't fit in a usize
+  fn total(&self) -> Option<usize> { usize::try_from(self.total?).ok() }
+
+  /// The state read as a mixed-radix number with the first node as the lowest digit,
+  /// which is its position when counting with [Self::increment]. Only meaningful when [Self::total] fits
+  fn rank<S: PackedState>(&self, state: S) -> usize {
+    match self.encoding {
+      Encoding::BitPacked => self.powers.iter().enumerate()
+        .map(|(idx, power)| self.get(state, idx) as usize * *power as usize)
+        .sum(),
+      Encoding::MixedRadix => state.low_u128() as usize,
+    }
+  }
+
+  /// Inverse of [Self::rank]
+  fn unrank<S: PackedState>(&self, rank: usize) -> S {
+    self.pack(self.powers.iter().zip(&self.bases).map(|(power, base)| (rank as u128 / power % *base as u128) as u8).collect())
   }
-  
-  pub fn set(state: PackedState, idx: usize, value: u8, base: u8, length: usize) -> PackedState {
-    debug_assert!(idx < length);
-    debug_assert!(value < base);
 
-    let bits = Self::bits_per_digit(base);
-    let shift = idx * bits;
-    let mask = Self::digit_mask(base) << shift;
+  fn digit_mask<S: PackedState>(base: u8) -> S {
+    S::from_digit(((1u16 << Self::bits_per_digit(base)) - 1) as u8)
+  }
 
-    (state & !mask) | ((value as u128) << shift)
+  pub fn get<S: PackedState>(&self, state: S, idx: usize) -> u8 {
+    match self.encoding {
+      Encoding::BitPacked => ((state >> self.offsets[idx]) & Self::digit_mask(self.bases[idx])).low_digit(),
+      Encoding::MixedRadix => state.radix_digit(self.powers[idx], self.bases[idx]),
+    }
   }
 
-  pub fn to_vec(state: PackedState, base: u8, length: usize) -> Vec<u8> {
-    let mut vec = Vec::with_capacity(length);
-    for i in 0 .. length {
-      vec.push(Self::get(state, i, base, length));
+  pub fn set<S: PackedState>(&self, state: S, idx: usize, value: u8) -> S {
+    debug_assert!(value < self.bases[idx]);
+    if self.encoding == Encoding::MixedRadix {
+      return state.radix_add(self.powers[idx], value as i16 - self.get(state, idx) as i16)
     }
-    vec
+
+    let shift = self.offsets[idx];
+    let mask = Self::digit_mask::<S>(self.bases[idx]) << shift;
+
+    (state & !mask) | (S::from_digit(value) << shift)
+  }
+
+  pub fn unpack<S: PackedState>(&self, state: S) -> Vec<u8> {
+    (0 .. self.length()).map(|idx| self.get(state, idx)).collect()
   }
 
-  pub fn from_vec(vec: Vec<u8>, base: u8) -> PackedState {
-    let mut state = 0;
+  pub fn pack<S: PackedState>(&self, vec: Vec<u8>) -> S {
+    let mut state = S::default();
     for (idx, val) in vec.iter().enumerate() {
-      state = Self::set(state, idx, *val, base, vec.len());
+      state = self.set(state, idx, *val);
     }
     state
   }
 
-  pub fn increment(mut state: PackedState, base: u8, length: usize) -> Option<PackedState> {
-    let bits = Self::bits_per_digit(base);
-    let mask = Self::digit_mask(base);
-
-    let mut i = 0;
-    while i < length {
-      let shift = i * bits;
-      let digit = (state >> shift) & mask;
+  pub fn increment<S: PackedState>(&self, mut state: S) -> Option<S> {
+    // Counting is just adding one, up to the last state
+    if let (Encoding::MixedRadix, Some(total)) = (self.encoding, self.total) {
+      return (state.low_u128() + 1 < total).then(|| state.wrapping_add(S::from_digit(1)))
+    }
+    for (idx, base) in self.bases.iter().enumerate() {
+      let shift = self.offsets[idx];
+      let mask = Self::digit_mask::<S>(*base);
+      let digit = ((state >> shift) & mask).low_digit();
 
-      if digit + 1 < base as u128 {
-        state += 1u128 << shift;
+      if digit < base - 1 {
+        state = state.wrapping_add(S::from_digit(1) << shift);
         return Some(state);
       } else {
         // reset digit to 0
-        state &= !(mask << shift);
+        state = state & !(mask << shift);
       }
-
-      i += 1;
     }
 
     None
   }
 }
 
+fn sorted(set: AHashSet<usize>) -> Vec<usize> {
+  let mut vec: Vec<usize> = set.into_iter().collect();
+  vec.sort_unstable();
+  vec
+}
+
 // Returns a count of how many of each node value each state has
 // Per state, how many nodes have a value
 // result[state][value] = node_count
-pub fn frequency_analysis(states: &Vec<PackedState>, length: usize, max: u8) -> Vec<Vec<u32>> {
+fn frequency_analysis<S: PackedState>(states: &[S], ops: &StateOps, max: u8) -> Vec<Vec<u32>> {
   if states.is_empty() { return Vec::new() }
   let mut result = Vec::new();
   let base = max as usize + 1;
   for state in states {
     let mut count = vec![0; base];
-    for idx in 0 .. length { 
-      count[StateOps::get(*state, idx, base as u8, length) as usize] += 1;
+    for idx in 0 .. ops.length() {
+      count[ops.get(*state, idx) as usize] += 1;
     }
     result.push(count);
   }
@@ -0,0 +1,221 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::graph::{canonical_labeling, to_digraph6, to_graph6};
+use super::dense::DenseMeta;
+use super::{BubbleDag, Encoding, MoveRule, PackedState, StateData, StateOps, theorem};
+
+// Changes whenever the layout does, see StateData::to_bytes
+const MAGIC: &[u8; 8] = b"GXSTATE1";
+
+/// A directory of explored [StateData], one file each, see [super::explore].
+///
+/// Entries are keyed by the graph6 of the neighbors, digraph6 if they aren't symmetric,
+/// along with the maxes, the move rule, the theorems and whether only orbits were explored.
+/// The nodes are renumbered first, see [canonical_labeling], so relabeling a graph keeps its entry.
+/// States are stored by rank in that numbering, so one entry serves every [Encoding]
+pub struct Cache {
+  dir: PathBuf,
+}
+impl Default for Cache {
+  /// `cache` in the working directory
+  fn default() -> Self { Self::new("cache") }
+}
+impl Cache {
+  pub fn new(dir: impl Into<PathBuf>) -> Self { Self { dir: dir.into() } }
+
+  fn path(&self, key: &str) -> PathBuf { self.dir.join(format!("{:016x}.bin", fnv1a(key.as_bytes()))) }
+
+  /// None on a miss, or if the entry can't be read
+  pub(super) fn load<S: PackedState>(
+    &self, neighbors: &[Vec<usize>], maxes: &[u8], rule: MoveRule, encoding: Encoding, orbits: bool
+  ) -> Option< StateData<S> > {
+    let (key, labeling) = key::<S>(neighbors, maxes, rule, orbits);
+    let bytes = fs::read(self.path(&key)).ok()?;
+    let mut data = StateData::unexplored(neighbors.to_vec(), maxes.to_vec(), rule, encoding, orbits)?;
+    data.read_bytes(&mut Reader(&bytes), &key, &labeling)?;
+    Some(data)
+  }
+
+  /// Does nothing if the entry can't be written, it just gets explored again next time
+  pub(super) fn store<S: PackedState>(&self, data: &StateData<S>, orbits: bool) {
+    let maxes = data.ops.bases.iter().map(|base| base - 1).collect::<Vec<_>>();
+    let (key, labeling) = key::<S>(&data.neighbors, &maxes, data.rule, orbits);
+    let path = self.path(&key);
+    // Written next to the entry first so nobody reads half of it
+    let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+    let _ = fs::create_dir_all(&self.dir)
+      .and_then(|_| fs::write(&partial, data.to_bytes(&key, &labeling)))
+      .and_then(|_| fs::rename(&partial, &path))
+    ;
+  }
+}
+
+// The key of the renumbered graph, and the renumbering
+fn key<S: PackedState>(neighbors: &[Vec<usize>], maxes: &[u8], rule: MoveRule, orbits: bool) -> (String, Labeling) {
+  let colors = maxes.iter().map(|max| *max as usize).collect::<Vec<_>>();
+  // Graphs too big for the search keep their node order
+  let numbers = canonical_labeling(neighbors, &colors).unwrap_or_else(|| (0 .. neighbors.len()).collect());
+  let mut renumbered = vec![Vec::new(); neighbors.len()];
+  let mut renumbered_maxes = vec![0; maxes.len()];
+  for (node, list) in neighbors.iter().enumerate() {
+    renumbered[numbers[node]] = list.iter().map(|neighbor| numbers[*neighbor]).collect();
+    renumbered_maxes[numbers[node]] = maxes[node];
+  }
+
+  let symmetric = neighbors.iter().enumerate()
+    .all(|(node, list)| list.iter().all(|neighbor| neighbors[*neighbor].contains(&node)))
+  ;
+  let graph = if symmetric { to_graph6(renumbered) } else { to_digraph6(renumbered) };
+  let theorems = theorem::registered::<S>(rule).iter().map(|theorem| theorem.name().to_string()).collect::<Vec<_>>();
+  let key = format!("{} {graph} {renumbered_maxes:?} {rule:?} orbits {orbits} {theorems:?}", env!("CARGO_PKG_VERSION"));
+  (key, Labeling::new(numbers, &renumbered_maxes))
+}
+
+// Converts between states and their rank in the canonical numbering, see key
+struct Labeling {
+  numbers: Vec<usize>,
+  // Place value of each renumbered node's digit
+  powers: Vec<usize>,
+}
+impl Labeling {
+  fn new(numbers: Vec<usize>, renumbered_maxes: &[u8]) -> Self {
+    let powers = renumbered_maxes.iter()
+      .scan(1usize, |power, max| Some(std::mem::replace(power, power.wrapping_mul(*max as usize + 1))))
+      .collect()
+    ;
+    Self { numbers, powers }
+  }
+
+  fn rank<S: PackedState>(&self, ops: &StateOps, state: S) -> usize {
+    self.numbers.iter().enumerate().map(|(node, number)| ops.get(state, node) as usize * self.powers[*number]).sum()
+  }
+
+  // Inverse of Self::rank
+  fn state<S: PackedState>(&self, ops: &StateOps, rank: usize) -> S {
+    self.numbers.iter().enumerate().fold(S::default(), |state, (node, number)| {
+      ops.set(state, node, (rank / self.powers[*number] % ops.base(node) as usize) as u8)
+    })
+  }
+}
+
+// Stable across runs and platforms, unlike the hashers in std and ahash
+fn fnv1a(bytes: &[u8]) -> u64 {
+  bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+    let (head, rest) = self.0.split_at_checked(len)?;
+    self.0 = rest;
+    Some(head)
+  }
+
+  fn u32(&mut self) -> Option<usize> { Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?) as usize) }
+
+  // A length followed by that many u32s
+  fn list(&mut self) -> Option< Vec<usize> > {
+    let len = self.u32()?;
+    Some(self.take(len.checked_mul(4)?)?.chunks_exact(4).map(|word| u32::from_le_bytes(word.try_into().unwrap()) as usize).collect())
+  }
+}
+
+fn write_list(bytes: &mut Vec<u8>, list: impl ExactSizeIterator<Item = usize>) {
+  bytes.extend((list.len() as u32).to_le_bytes());
+  for value in list { bytes.extend((value as u32).to_le_bytes()) }
+}
+
+impl<S: PackedState> StateData<S> {
+  // The key, then the list of every rank, every bubble as ranks and the exits of the DAG.
+  // Lists are in rank order, so they don't need their states. Ranks are in the canonical numbering
+  fn to_bytes(&self, key: &str, labeling: &Labeling) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend((key.len() as u32).to_le_bytes());
+    bytes.extend(key.as_bytes());
+    let mut lists = vec![DenseMeta::UNTRACKED; self.meta.len()];
+    for (list, states) in self.states.iter().enumerate() {
+      for state in states { lists[labeling.rank(&self.ops, *state)] = list as u8 }
+    }
+    bytes.extend(lists);
+    bytes.extend((self.bubbles.len() as u32).to_le_bytes());
+    for bubble in &self.bubbles { write_list(&mut bytes, bubble.iter().map(|state| labeling.rank(&self.ops, *state))) }
+    bytes.extend((self.dag.exits.len() as u32).to_le_bytes());
+    for exits in &self.dag.exits { write_list(&mut bytes, exits.iter().copied()) }
+    bytes
+  }
+
+  // Inverse of Self::to_bytes, None if the bytes are for another key or don't add up.
+  // With orbits each state read stands in for its orbit, see Self::canonical
+  fn read_bytes(&mut self, reader: &mut Reader, key: &str, labeling: &Labeling) -> Option<()> {
+    if reader.take(MAGIC.len())? != MAGIC { return None }
+    let key_len = reader.u32()?;
+    if reader.take(key_len)? != key.as_bytes() { return None }
+
+    // Back in rank order, so the lists come out like they would exploring
+    let mut lists = vec![DenseMeta::UNTRACKED; self.meta.len()];
+    for (rank, list) in reader.take(self.meta.len())?.iter().enumerate() {
+      if *list == DenseMeta::UNTRACKED { continue }
+      lists[self.ops.rank(self.canonical(labeling.state(&self.ops, rank)))] = *list;
+    }
+    let mut state = S::default();
+    for (rank, list) in lists.iter().enumerate() {
+      if rank > 0 { state = self.ops.increment(state)? }
+      if *list == DenseMeta::UNTRACKED { continue }
+      let states = self.states.get_mut(*list as usize)?;
+      self.meta.set_classification(rank, *list as usize, states.len());
+      states.push(state);
+    }
+
+    let tracked = self.states.iter().map(Vec::len).sum::<usize>();
+    for bubble_idx in 0 .. reader.u32()? {
+      let bubble = reader.list()?;
+      let mut states = Vec::with_capacity(bubble.len());
+      for (idx, rank) in bubble.into_iter().enumerate() {
+        if rank >= self.meta.len() { return None }
+        let rank = self.ops.rank(self.canonical(labeling.state(&self.ops, rank)));
+        let (list, list_idx) = self.meta.classification(rank)?;
+        self.meta.set_bubble(rank, bubble_idx, idx);
+        states.push(self.states[list][list_idx]);
+      }
+      self.bubbles.push(states);
+    }
+    if self.bubbles.iter().map(Vec::len).sum::<usize>() != tracked { return None }
+
+    let exits = (0 .. reader.u32()?).map(|_| reader.list()).collect::<Option<Vec<_>>>()?;
+    let big_bubbles = self.bubbles.len().checked_sub(1)?;
+    if exits.len() != big_bubbles + self.bubbles[big_bubbles].len() { return None }
+    if exits.iter().flatten().any(|id| *id >= exits.len()) { return None }
+    self.dag = BubbleDag::new(big_bubbles, exits);
+    Some(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::{Encoding, MoveRule, Progress, StateData};
+  use super::Cache;
+
+  #[test]
+  fn large_graph_round_trips() {
+    // A path past the single byte graph6 header, only the ends can move
+    let n = 70;
+    let neighbors = (0 .. n).map(|node: usize| {
+      [node.checked_sub(1), Some(node + 1).filter(|next| *next < n)].into_iter().flatten().collect()
+    }).collect::<Vec< Vec<usize> >>();
+    let mut maxes = vec![0; n];
+    for node in [0, 1, n - 2, n - 1] { maxes[node] = 2 }
+    let rule = MoveRule::default();
+
+    let cache = Cache::new(std::env::temp_dir().join(format!("graph-cache-test-{}", std::process::id())));
+    let explored = StateData::<u64>::new(neighbors.clone(), maxes.clone(), rule, Encoding::BitPacked, false, None, &Progress::default())
+      .unwrap();
+    cache.store(&explored, false);
+    let loaded = cache.load::<u64>(&neighbors, &maxes, rule, Encoding::BitPacked, false);
+    let _ = std::fs::remove_dir_all(&cache.dir);
+    let loaded = loaded.unwrap();
+    assert_eq!(explored.states, loaded.states);
+    assert_eq!(explored.bubbles, loaded.bubbles);
+    assert_eq!(explored.dag.exits, loaded.dag.exits);
+  }
+}
@@ -0,0 +1,142 @@
+/// Bubbles as vertices, with an edge wherever an irreversible move leads out of one bubble into another.
+///
+/// Every bubble of size 1 gets its own id after the bigger bubbles, see [Self::id].
+/// Irreversible moves can cycle between bubbles, so those cycles are condensed into groups,
+/// and the groups form a DAG.
+pub struct BubbleDag {
+  // Bubbles with more than one state, the last bubble in StateData::bubbles holds the rest
+  big_bubbles: usize,
+  /// Indexed by bubble id
+  pub exits: Vec< Vec<usize> >,
+  pub entries: Vec< Vec<usize> >,
+  /// Which group each bubble id belongs to
+  pub group: Vec<usize>,
+  /// Bubble ids in each group, groups are in topological order
+  pub groups: Vec< Vec<usize> >,
+  /// Groups which can't be entered from another group
+  pub sources: Vec<usize>,
+  /// Groups which can't be exited into another group
+  pub sinks: Vec<usize>,
+  /// One of the longest paths through the groups
+  pub longest_chain: Vec<usize>,
+}
+impl BubbleDag {
+  /// `exits[id]` lists the bubble ids reachable from `id` with one move
+  pub fn new(big_bubbles: usize, exits: Vec< Vec<usize> >) -> Self {
+    let mut entries = vec![Vec::new(); exits.len()];
+    for (from, to_list) in exits.iter().enumerate() {
+      for to in to_list { entries[*to].push(from) }
+    }
+
+    let groups = strongly_connected(&exits);
+    let mut group = vec![0; exits.len()];
+    for (group_idx, members) in groups.iter().enumerate() {
+      for member in members { group[*member] = group_idx }
+    }
+
+    let mut group_exits = vec![Vec::new(); groups.len()];
+    let mut has_entry = vec![false; groups.len()];
+    for (from, to_list) in exits.iter().enumerate() {
+      for to in to_list {
+        let (from, to) = (group[from], group[*to]);
+        if from == to { continue }
+        group_exits[from].push(to);
+        has_entry[to] = true;
+      }
+    }
+    let sources = (0 .. groups.len()).filter(|group| !has_entry[*group]).collect();
+    let sinks = (0 .. groups.len()).filter(|group| group_exits[*group].is_empty()).collect();
+
+    // Groups are topologically sorted, so every edge points forwards
+    let mut chain_length = vec![1usize; groups.len()];
+    let mut previous = vec![None; groups.len()];
+    for from in 0 .. groups.len() {
+      for to in &group_exits[from] {
+        if chain_length[from] + 1 > chain_length[*to] {
+          chain_length[*to] = chain_length[from] + 1;
+          previous[*to] = Some(from);
+        }
+      }
+    }
+    let mut longest_chain = Vec::new();
+    let mut cur = (0 .. groups.len()).max_by_key(|group| chain_length[*group]);
+    while let Some(group) = cur {
+      longest_chain.push(group);
+      cur = previous[group];
+    }
+    longest_chain.reverse();
+
+    Self { big_bubbles, exits, entries, group, groups, sources, sinks, longest_chain }
+  }
+
+  /// The bubble id of a state given its (bubble_idx, state_idx)
+  pub fn id(&self, bubble_idx: usize, state_idx: usize) -> usize {
+    if bubble_idx < self.big_bubbles { bubble_idx } else { self.big_bubbles + state_idx }
+  }
+
+  /// Inverse of [Self::id], the state_idx is 0 for bubbles bigger than 1
+  pub fn location(&self, id: usize) -> (usize, usize) {
+    if id < self.big_bubbles { (id, 0) } else { (self.big_bubbles, id - self.big_bubbles) }
+  }
+
+  pub fn is_single(&self, id: usize) -> bool { id >= self.big_bubbles }
+}
+
+// Tarjan's algorithm without recursion, returns the components in topological order
+fn strongly_connected(exits: &[Vec<usize>]) -> Vec< Vec<usize> > {
+  const UNVISITED: usize = usize::MAX;
+  let count = exits.len();
+  let mut index = vec![UNVISITED; count];
+  let mut low_link = vec![0; count];
+  let mut on_stack = vec![false; count];
+  let mut stack = Vec::new();
+  let mut components = Vec::new();
+  let mut next_index = 0;
+
+  for root in 0 .. count {
+    if index[root] != UNVISITED { continue }
+    // (vertex, next exit to look at)
+    let mut call_stack = vec![(root, 0)];
+    index[root] = next_index;
+    low_link[root] = next_index;
+    next_index += 1;
+    stack.push(root);
+    on_stack[root] = true;
+
+    while let Some((vertex, exit_idx)) = call_stack.last_mut() {
+      let vertex = *vertex;
+      if let Some(&next) = exits[vertex].get(*exit_idx) {
+        *exit_idx += 1;
+        if index[next] == UNVISITED {
+          index[next] = next_index;
+          low_link[next] = next_index;
+          next_index += 1;
+          stack.push(next);
+          on_stack[next] = true;
+          call_stack.push((next, 0));
+        } else if on_stack[next] {
+          low_link[vertex] = low_link[vertex].min(index[next]);
+        }
+        continue;
+      }
+
+      call_stack.pop();
+      if let Some((parent, _)) = call_stack.last() {
+        low_link[*parent] = low_link[*parent].min(low_link[vertex]);
+      }
+      if low_link[vertex] == index[vertex] {
+        let mut component = Vec::new();
+        while let Some(member) = stack.pop() {
+          on_stack[member] = false;
+          component.push(member);
+          if member == vertex { break }
+        }
+        components.push(component);
+      }
+    }
+  }
+
+  // Tarjan finds sinks first
+  components.reverse();
+  components
+}
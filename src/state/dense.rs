@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+/// Metadata of every state in flat arrays indexed by its rank, see [super::StateOps::rank].
+///
+/// The whole space gets enumerated anyway, so this takes a few bytes a state where a hash map took dozens.
+/// States that aren't tracked, like the ones skipped when only exploring orbits, have no classification.
+pub struct DenseMeta {
+  // Index into StateData::states, UNTRACKED if the state isn't tracked
+  lists: Vec<u8>,
+  list_positions: Vec<u32>,
+  bubbles: Vec<u32>,
+  bubble_positions: Vec<u32>,
+}
+impl DenseMeta {
+  pub const UNTRACKED: u8 = u8::MAX;
+
+  /// None if there are too many states to index, or not enough memory for them
+  pub fn new(total: usize) -> Option<Self> {
+    u32::try_from(total).ok()?;
+    Some(Self {
+      lists: filled(total, Self::UNTRACKED)?,
+      list_positions: filled(total, 0)?,
+      bubbles: filled(total, 0)?,
+      bubble_positions: filled(total, 0)?,
+    })
+  }
+
+  pub fn len(&self) -> usize { self.lists.len() }
+
+  pub fn is_tracked(&self, rank: usize) -> bool { self.lists[rank] != Self::UNTRACKED }
+
+  /// (list, index in list)
+  pub fn classification(&self, rank: usize) -> Option<(usize, usize)> {
+    self.is_tracked(rank).then(|| (self.lists[rank] as usize, self.list_positions[rank] as usize))
+  }
+
+  pub fn set_classification(&mut self, rank: usize, list: usize, idx: usize) {
+    debug_assert!(list < Self::UNTRACKED as usize);
+    self.lists[rank] = list as u8;
+    self.list_positions[rank] = idx as u32;
+  }
+
+  /// (bubble, index in bubble), only meaningful for tracked states once bubbles are identified
+  pub fn bubble(&self, rank: usize) -> (usize, usize) {
+    (self.bubbles[rank] as usize, self.bubble_positions[rank] as usize)
+  }
+
+  pub fn set_bubble(&mut self, rank: usize, bubble: usize, idx: usize) {
+    self.bubbles[rank] = bubble as u32;
+    self.bubble_positions[rank] = idx as u32;
+  }
+}
+
+// `total` copies of `value`, None instead of aborting if they can't be allocated
+fn filled<T: Clone>(total: usize, value: T) -> Option< Vec<T> > {
+  let mut vec = Vec::new();
+  vec.try_reserve_exact(total).ok()?;
+  vec.resize(total, value);
+  Some(vec)
+}
+
+/// One bit per rank, for marking states seen during a search
+pub struct Bitset {
+  words: Vec<u64>,
+}
+impl Bitset {
+  pub fn new(len: usize) -> Self { Self { words: vec![0; len.div_ceil(64)] } }
+
+  pub fn contains(&self, idx: usize) -> bool { self.words[idx / 64] >> (idx % 64) & 1 == 1 }
+
+  /// True if `idx` wasn't in the set already
+  pub fn insert(&mut self, idx: usize) -> bool {
+    let (word, bit) = (&mut self.words[idx / 64], 1 << (idx % 64));
+    let inserted = *word & bit == 0;
+    *word |= bit;
+    inserted
+  }
+}
+
+/// [Bitset] that threads can insert into at once
+pub struct AtomicBitset {
+  words: Vec<AtomicU64>,
+}
+impl AtomicBitset {
+  pub fn new(len: usize) -> Self { Self { words: (0 .. len.div_ceil(64)).map(|_| AtomicU64::new(0)).collect() } }
+
+  /// True if `idx` wasn't in the set already, only one thread gets true for the same `idx`
+  pub fn insert(&self, idx: usize) -> bool {
+    let bit = 1 << (idx % 64);
+    self.words[idx / 64].fetch_or(bit, Relaxed) & bit == 0
+  }
+}
+impl From<AtomicBitset> for Bitset {
+  fn from(set: AtomicBitset) -> Self { Self { words: set.words.into_iter().map(AtomicU64::into_inner).collect() } }
+}
+
+/// Where a search marks the states it's seen, see [super::StateData::dfs]
+pub trait Marks {
+  /// True if `idx` wasn't marked already
+  fn mark(&mut self, idx: usize) -> bool;
+}
+impl Marks for Bitset {
+  fn mark(&mut self, idx: usize) -> bool { self.insert(idx) }
+}
+impl Marks for &AtomicBitset {
+  fn mark(&mut self, idx: usize) -> bool { self.insert(idx) }
+}
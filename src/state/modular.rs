@@ -0,0 +1,204 @@
+use super::MoveRule;
+
+/// Exact solver for the wrapping rule, see [super::Boundary::Wrap].
+///
+/// Wrapping moves add a column of the matrix M of move deltas, each node mod its own base,
+/// so a state x is valid exactly when M y = x has a solution, with y counting how often each center gets pressed.
+/// Everything is worked out mod the lcm of the bases, with each node's row scaled up by lcm / base.
+/// M is diagonalized once as U M V = D with U and V invertible mod the lcm, the Smith normal form
+/// without the divisibility chain, which also works when it isn't prime.
+pub struct ModularSolver {
+  // The lcm of the bases
+  base: i64,
+  // lcm / base of each node
+  scales: Vec<i64>,
+  row_ops: Vec< Vec<i64> >,
+  column_ops: Vec< Vec<i64> >,
+  diagonal: Vec<i64>,
+}
+impl ModularSolver {
+  /// The boundary of `rule` is ignored, only its deltas matter
+  pub fn new(neighbors: &[Vec<usize>], bases: &[u8], rule: MoveRule) -> Self {
+    let n = neighbors.len();
+    let base = bases.iter().fold(1, |lcm, base| lcm / extended_gcd(lcm, *base as i64).0 * *base as i64);
+    let scales = bases.iter().map(|node_base| base / *node_base as i64).collect::<Vec<_>>();
+    let mut matrix = vec![vec![0; n]; n];
+    let deltas = (0 .. n).flat_map(|center| rule.affected(neighbors, center).map(move |(node, delta)| (node, center, delta)));
+    for (node, center, delta) in deltas { matrix[node][center] = (delta as i64 * scales[node]).rem_euclid(base) }
+    let mut solver = Self { base, scales, row_ops: identity(n), column_ops: identity(n), diagonal: Vec::new() };
+    solver.diagonalize(&mut matrix);
+    solver.diagonal = (0 .. n).map(|idx| matrix[idx][idx]).collect();
+    solver
+  }
+
+  fn diagonalize(&mut self, matrix: &mut [Vec<i64>]) {
+    let n = matrix.len();
+    for k in 0 .. n {
+      // The smallest entry left makes for the fewest gcd steps
+      let pivot = (k .. n)
+        .flat_map(|row| (k .. n).map(move |column| (row, column)))
+        .filter(|(row, column)| matrix[*row][*column] != 0)
+        .min_by_key(|(row, column)| matrix[*row][*column])
+      ;
+      let Some((row, column)) = pivot else { return };
+      matrix.swap(k, row);
+      self.row_ops.swap(k, row);
+      for line in matrix.iter_mut().chain(self.column_ops.iter_mut()) { line.swap(k, column) }
+
+      // Every step that doesn't just eliminate shrinks the pivot, so this ends
+      loop {
+        for row in k + 1 .. n {
+          if matrix[row][k] != 0 { self.combine_rows(matrix, k, row) }
+        }
+        for column in k + 1 .. n {
+          if matrix[k][column] != 0 { self.combine_columns(matrix, k, column) }
+        }
+        if (k + 1 .. n).all(|row| matrix[row][k] == 0) { break }
+      }
+    }
+  }
+
+  // Leaves gcd(pivot, entry) at the pivot and 0 at the entry, with a transform of determinant 1.
+  // Plain elimination when the pivot divides the entry, so the pivot's other line stays cleared
+  fn transform(&self, pivot: i64, entry: i64) -> [i64; 4] {
+    if entry % pivot == 0 { return [1, 0, -entry / pivot, 1] }
+    let (gcd, s, t) = extended_gcd(pivot, entry);
+    [s, t, -entry / gcd, pivot / gcd]
+  }
+
+  fn combine_rows(&mut self, matrix: &mut [Vec<i64>], pivot: usize, row: usize) {
+    let [s, t, u, v] = self.transform(matrix[pivot][pivot], matrix[row][pivot]);
+    for lines in [&mut *matrix, &mut self.row_ops] {
+      // Pivots always come before the rows they clear
+      let (above, below) = lines.split_at_mut(row);
+      for (a, b) in above[pivot].iter_mut().zip(&mut below[0]) {
+        (*a, *b) = ((s * *a + t * *b).rem_euclid(self.base), (u * *a + v * *b).rem_euclid(self.base));
+      }
+    }
+  }
+
+  fn combine_columns(&mut self, matrix: &mut [Vec<i64>], pivot: usize, column: usize) {
+    let [s, t, u, v] = self.transform(matrix[pivot][pivot], matrix[pivot][column]);
+    for lines in [&mut *matrix, &mut self.column_ops] {
+      for line in lines.iter_mut() {
+        let (a, b) = (line[pivot], line[column]);
+        line[pivot] = (s * a + t * b).rem_euclid(self.base);
+        line[column] = (u * a + v * b).rem_euclid(self.base);
+      }
+    }
+  }
+
+  /// Presses are taken mod this
+  pub fn modulus(&self) -> u16 { self.base as u16 }
+
+  /// How many times to press each center, with +1 moves, to get from all zeros to `target`.
+  /// None if `target` can't be reached
+  pub fn presses(&self, target: &[u8]) -> Option< Vec<u16> > {
+    let n = self.diagonal.len();
+    let target = target.iter().zip(&self.scales).map(|(value, scale)| *value as i64 * scale).collect::<Vec<_>>();
+    // D z = U x, then y = V z
+    let mut z = vec![0; n];
+    for (idx, row) in self.row_ops.iter().enumerate() {
+      let value = row.iter().zip(&target).map(|(a, b)| a * b).sum::<i64>().rem_euclid(self.base);
+      let gcd = extended_gcd(self.diagonal[idx], self.base).0;
+      if value % gcd != 0 { return None }
+      let modulus = self.base / gcd;
+      z[idx] = (value / gcd * inverse(self.diagonal[idx] / gcd, modulus)).rem_euclid(modulus);
+    }
+    Some(self.column_ops.iter()
+      .map(|row| row.iter().zip(&z).map(|(a, b)| a * b).sum::<i64>().rem_euclid(self.base) as u16)
+      .collect()
+    )
+  }
+
+  /// Size of the valid set, None if it doesn't fit
+  pub fn reachable_count(&self) -> Option<u128> {
+    self.diagonal.iter().try_fold(1u128, |count, entry| {
+      count.checked_mul((self.base / extended_gcd(*entry, self.base).0) as u128)
+    })
+  }
+}
+
+fn identity(n: usize) -> Vec< Vec<i64> > {
+  (0 .. n).map(|row| (0 .. n).map(|column| (row == column) as i64).collect()).collect()
+}
+
+// (gcd, s, t) with s * a + t * b = gcd, for a and b that aren't both 0
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+  if b == 0 { return (a, 1, 0) }
+  let (gcd, s, t) = extended_gcd(b, a % b);
+  (gcd, t, s - a / b * t)
+}
+
+// Of a mod modulus, when they're coprime
+fn inverse(a: i64, modulus: i64) -> i64 {
+  if modulus == 1 { return 0 }
+  extended_gcd(a.rem_euclid(modulus), modulus).1.rem_euclid(modulus)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::{Boundary, Classification, Encoding, MoveRule, Neighborhood, PackedState, Progress, StateData};
+
+  // Every valid state has presses which replay to it, every invalid state is proven invalid,
+  // and without orbits the valid list is as long as the solver says
+  fn solver_agrees<S: PackedState>(data: &StateData<S>) -> bool {
+    let Some(solver) = data.solver() else { return false };
+    let replays = data.get_list(Classification::Valid).iter().all(|state| {
+      let Some(presses) = solver.presses(&data.parse_state(*state)) else { return false };
+      let replayed = presses.iter().enumerate().fold(S::default(), |replayed, (center, presses)| {
+        (0 .. *presses).fold(replayed, |replayed, _| data.splash_state(replayed, center, 1, false).unwrap())
+      });
+      data.canonical(replayed) == *state
+    });
+    let count = data.symmetry.is_some()
+      || solver.reachable_count() == Some(data.get_list(Classification::Valid).len() as u128);
+    replays && count && data.get_list(Classification::InvalidOther).is_empty()
+  }
+
+  fn undirected(n: usize, edges: &[(usize, usize)]) -> Vec< Vec<usize> > {
+    let mut neighbors = vec![Vec::new(); n];
+    for (a, b) in edges {
+      neighbors[*a].push(*b);
+      neighbors[*b].push(*a);
+    }
+    neighbors
+  }
+
+  fn rule(neighborhood: Neighborhood, center_delta: i8, neighbor_delta: i8) -> MoveRule {
+    MoveRule { neighborhood, center_delta, neighbor_delta, boundary: Boundary::Wrap }
+  }
+
+  #[test]
+  fn solver_agrees_with_search() {
+    let graphs = [
+      undirected(3, &[(0, 1), (1, 2)]),
+      undirected(4, &[(0, 1), (1, 2), (2, 3), (3, 0)]),
+      undirected(4, &[(0, 1), (0, 2), (0, 3)]),
+      undirected(4, &[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]),
+      undirected(4, &[(0, 1), (1, 2), (2, 0), (2, 3)]),
+    ];
+    let rules = [
+      rule(Neighborhood::Closed, 1, 1),
+      rule(Neighborhood::Open, 1, 1),
+      rule(Neighborhood::Closed, 2, 1),
+      rule(Neighborhood::Closed, 1, 3),
+      rule(Neighborhood::Open, 1, -2),
+    ];
+    for neighbors in &graphs {
+      let n = neighbors.len();
+      // Prime bases, composite bases, and a different max on each node
+      let maxes = [vec![1; n], vec![2; n], vec![3; n], vec![5; n], (0 .. n).map(|node| node as u8 % 4 + 1).collect()];
+      for maxes in maxes {
+        for rule in rules {
+          for orbits in [false, true] {
+            let data = StateData::<u64>::new(
+              neighbors.clone(), maxes.clone(), rule, Encoding::BitPacked, orbits, None, &Progress::default()
+            ).unwrap();
+            assert!(solver_agrees(&data), "{neighbors:?} with maxes {maxes:?}, {rule}, orbits {orbits}");
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,191 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
+
+/// The widest state the explorer can hold, used to pass states around without
+/// knowing which backend a [super::StateData] picked.
+pub type AnyState = WideState<4>;
+
+/// Fixed width integer that a state gets packed into.
+/// The backend is picked by [super::explore] based on how many bits the graph needs.
+pub trait PackedState:
+  Copy + Default + Eq + Ord + Hash + Debug + Send + Sync + 'static
+  + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> + Not<Output = Self>
+  + Shl<usize, Output = Self> + Shr<usize, Output = Self>
+{
+  const BITS: usize;
+
+  fn from_digit(value: u8) -> Self;
+  /// Truncates to the lowest 8 bits
+  fn low_digit(self) -> u8;
+  fn wrapping_add(self, other: Self) -> Self;
+  fn wrapping_sub(self, other: Self) -> Self;
+
+  /// The lowest 128 bits
+  fn low_u128(self) -> u128;
+  /// `(self / power) % base`, a digit of a mixed-radix state, which always fits in 128 bits
+  fn radix_digit(self, power: u128, base: u8) -> u8;
+  /// Adds `value * power` to a mixed-radix state, wrapping
+  fn radix_add(self, power: u128, value: i16) -> Self;
+
+  fn widen(self) -> AnyState;
+  /// Truncates anything that doesn't fit
+  fn narrow(state: AnyState) -> Self;
+}
+
+macro_rules! impl_packed_state {
+  ($ty:ty) => {
+    impl PackedState for $ty {
+      const BITS: usize = <$ty>::BITS as usize;
+
+      fn from_digit(value: u8) -> Self { value as $ty }
+      fn low_digit(self) -> u8 { self as u8 }
+      fn wrapping_add(self, other: Self) -> Self { <$ty>::wrapping_add(self, other) }
+      fn wrapping_sub(self, other: Self) -> Self { <$ty>::wrapping_sub(self, other) }
+
+      fn low_u128(self) -> u128 { self as u128 }
+      fn radix_digit(self, power: u128, base: u8) -> u8 { (self / power as $ty % base as $ty) as u8 }
+      fn radix_add(self, power: u128, value: i16) -> Self {
+        // Negative values sign extend, so this wraps around to a subtraction
+        self.wrapping_add((power as $ty).wrapping_mul(value as $ty))
+      }
+
+      fn widen(self) -> AnyState {
+        let mut words = [0; 4];
+        let mut value = self;
+        for word in words.iter_mut().take(<Self as PackedState>::BITS.div_ceil(64)) {
+          *word = value as u64;
+          value = value.checked_shr(64).unwrap_or(0);
+        }
+        WideState(words)
+      }
+
+      fn narrow(state: AnyState) -> Self {
+        let mut value: $ty = 0;
+        for word in state.0.iter().take(<Self as PackedState>::BITS.div_ceil(64)).rev() {
+          value = value.checked_shl(64).unwrap_or(0) | *word as $ty;
+        }
+        value
+      }
+    }
+  };
+}
+impl_packed_state!(u64);
+impl_packed_state!(u128);
+
+/// Multi-word state for graphs that don't fit in a u128.
+/// Words are little endian, `self.0[0]` holds the lowest digits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WideState<const W: usize>(pub [u64; W]);
+impl<const W: usize> Default for WideState<W> {
+  fn default() -> Self { Self([0; W]) }
+}
+impl<const W: usize> PartialOrd for WideState<W> {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> { Some(self.cmp(other)) }
+}
+impl<const W: usize> Ord for WideState<W> {
+  // Compare as a number, most significant word first
+  fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    self.0.iter().rev().cmp(other.0.iter().rev())
+  }
+}
+
+impl<const W: usize> BitAnd for WideState<W> {
+  type Output = Self;
+  fn bitand(mut self, rhs: Self) -> Self {
+    for (word, other) in self.0.iter_mut().zip(rhs.0) { *word &= other }
+    self
+  }
+}
+impl<const W: usize> BitOr for WideState<W> {
+  type Output = Self;
+  fn bitor(mut self, rhs: Self) -> Self {
+    for (word, other) in self.0.iter_mut().zip(rhs.0) { *word |= other }
+    self
+  }
+}
+impl<const W: usize> BitXor for WideState<W> {
+  type Output = Self;
+  fn bitxor(mut self, rhs: Self) -> Self {
+    for (word, other) in self.0.iter_mut().zip(rhs.0) { *word ^= other }
+    self
+  }
+}
+impl<const W: usize> Not for WideState<W> {
+  type Output = Self;
+  fn not(mut self) -> Self {
+    for word in self.0.iter_mut() { *word = !*word }
+    self
+  }
+}
+impl<const W: usize> Shl<usize> for WideState<W> {
+  type Output = Self;
+  fn shl(self, shift: usize) -> Self {
+    let (words, bits) = (shift / 64, shift % 64);
+    let mut out = [0; W];
+    for idx in (words .. W).rev() {
+      out[idx] = self.0[idx - words] << bits;
+      if bits != 0 && idx > words { out[idx] |= self.0[idx - words - 1] >> (64 - bits) }
+    }
+    Self(out)
+  }
+}
+impl<const W: usize> Shr<usize> for WideState<W> {
+  type Output = Self;
+  fn shr(self, shift: usize) -> Self {
+    let (words, bits) = (shift / 64, shift % 64);
+    let mut out = [0; W];
+    for (idx, word) in out.iter_mut().enumerate().take(W.saturating_sub(words)) {
+      *word = self.0[idx + words] >> bits;
+      if bits != 0 && idx + words + 1 < W { *word |= self.0[idx + words + 1] << (64 - bits) }
+    }
+    Self(out)
+  }
+}
+
+impl<const W: usize> PackedState for WideState<W> {
+  const BITS: usize = 64 * W;
+
+  fn from_digit(value: u8) -> Self {
+    let mut out = Self::default();
+    out.0[0] = value as u64;
+    out
+  }
+  fn low_digit(self) -> u8 { self.0[0] as u8 }
+
+  fn wrapping_add(mut self, other: Self) -> Self {
+    let mut carry = false;
+    for (word, other) in self.0.iter_mut().zip(other.0) {
+      let (sum, overflow_a) = word.overflowing_add(other);
+      let (sum, overflow_b) = sum.overflowing_add(carry as u64);
+      *word = sum;
+      carry = overflow_a || overflow_b;
+    }
+    self
+  }
+
+  // Adds the two's complement
+  fn wrapping_sub(self, other: Self) -> Self { self.wrapping_add((!other).wrapping_add(Self::from_digit(1))) }
+
+  fn low_u128(self) -> u128 { self.0[0] as u128 | (self.0.get(1).copied().unwrap_or(0) as u128) << 64 }
+  fn radix_digit(self, power: u128, base: u8) -> u8 { (self.low_u128() / power % base as u128) as u8 }
+  // Only the low 128 bits can be set
+  fn radix_add(mut self, power: u128, value: i16) -> Self {
+    let sum = self.low_u128().wrapping_add(power.wrapping_mul(value as u128));
+    self.0[0] = sum as u64;
+    if let Some(word) = self.0.get_mut(1) { *word = (sum >> 64) as u64 }
+    self
+  }
+
+  fn widen(self) -> AnyState {
+    let mut out = AnyState::default();
+    for (word, own) in out.0.iter_mut().zip(self.0) { *word = own }
+    out
+  }
+
+  fn narrow(state: AnyState) -> Self {
+    let mut out = Self::default();
+    for (word, any) in out.0.iter_mut().zip(state.0) { *word = any }
+    out
+  }
+}
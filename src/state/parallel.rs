@@ -0,0 +1,256 @@
+use std::mem::{replace, take};
+use std::num::NonZero;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering::{AcqRel, Acquire, Relaxed}};
+use std::thread;
+
+use super::dense::{AtomicBitset, Bitset};
+use super::{Classification, PackedState, Progress, REPORT_INTERVAL, StateData};
+
+// Below this many states the threads cost more than they save
+const PARALLEL_STATES: usize = 1 << 16;
+
+/// How many threads to explore `total` states with, 1 meaning the serial path
+pub fn threads(total: usize) -> usize {
+  if total < PARALLEL_STATES { return 1 }
+  thread::available_parallelism().map_or(1, NonZero::get)
+}
+
+/// Splits `0 .. len` into one contiguous range per thread and maps each on its own thread,
+/// the results come back in range order
+fn map_ranges<R: Send>(len: usize, threads: usize, map: impl Fn(Range<usize>) -> R + Sync) -> Vec<R> {
+  let size = len.div_ceil(threads).max(1);
+  let map = &map;
+  thread::scope(|scope| {
+    let workers = (0 .. len).step_by(size)
+      .map(|start| scope.spawn(move || map(start .. (start + size).min(len))))
+      .collect::<Vec<_>>()
+    ;
+    workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+  })
+}
+
+/// Disjoint sets that threads can join at once, each set's root is its smallest element
+struct UnionFind {
+  // Never larger than the element itself
+  parents: Vec<AtomicU32>,
+}
+impl UnionFind {
+  fn new(len: usize) -> Self { Self { parents: (0 .. len as u32).map(AtomicU32::new).collect() } }
+
+  fn find(&self, mut idx: u32) -> u32 {
+    loop {
+      let parent = self.parents[idx as usize].load(Acquire);
+      if parent == idx { return idx }
+      // Halves the path, losing the race just means someone else shortened it
+      let grandparent = self.parents[parent as usize].load(Acquire);
+      let _ = self.parents[idx as usize].compare_exchange(parent, grandparent, AcqRel, Acquire);
+      idx = grandparent;
+    }
+  }
+
+  fn union(&self, mut a: u32, mut b: u32) {
+    loop {
+      (a, b) = (self.find(a), self.find(b));
+      if a == b { return }
+      let (low, high) = (a.min(b), a.max(b));
+      // Fails if `high` stopped being a root in the meantime
+      if self.parents[high as usize].compare_exchange(high, low, AcqRel, Acquire).is_ok() { return }
+    }
+  }
+}
+
+/// Parallel versions of the phases of [StateData::new], taken when [StateData::threads] is more than 1.
+/// Each ends up with exactly what the serial version would
+impl<S: PackedState> StateData<S> {
+  // Breadth first, every thread expands its share of the frontier
+  pub(super) fn generate_valid_parallel(&self, progress: &Progress) -> Bitset {
+    let valid = AtomicBitset::new(self.meta.len());
+    valid.insert(self.ops.rank(S::default()));
+    progress.advance(1);
+    let mut frontier = vec![S::default()];
+    while !frontier.is_empty() && !progress.is_cancelled() {
+      frontier = map_ranges(frontier.len(), self.threads, |range| {
+        let mut next = Vec::new();
+        for chunk in frontier[range].chunks(REPORT_INTERVAL as usize) {
+          let found = next.len();
+          for state in chunk {
+            for center in 0 .. self.length() {
+              for operation in [-1, 1] {
+                let Some(new_state) = self.splash_state(*state, center, operation, false) else { continue };
+                let new_state = self.canonical(new_state);
+                if valid.insert(self.ops.rank(new_state)) { next.push(new_state) }
+              }
+            }
+          }
+          progress.advance((next.len() - found) as u64);
+          if progress.is_cancelled() { break }
+        }
+        next
+      }).concat();
+    }
+    valid.into()
+  }
+
+  // Every thread counts through its own range of ranks
+  pub(super) fn generate_invalid_parallel(&mut self, valid: &Bitset, progress: &Progress) -> Vec<S> {
+    let this = &*self;
+    let ranges = map_ranges(this.meta.len(), this.threads, |range| {
+      let (mut valid_states, mut missing) = (Vec::new(), Vec::new());
+      let mut state = this.ops.unrank(range.start);
+      let mut unreported = 0;
+      for rank in range {
+        debug_assert_eq!(rank, this.ops.rank(state));
+        if valid.contains(rank) { valid_states.push(state) }
+        else if this.is_canonical(state) { missing.push(state) }
+        // Past the last state there's nothing left in the range anyway
+        if let Some(next) = this.ops.increment(state) { state = next }
+        unreported += 1;
+        if unreported == REPORT_INTERVAL {
+          progress.advance(take(&mut unreported));
+          if progress.is_cancelled() { break }
+        }
+      }
+      progress.advance(unreported);
+      (valid_states, missing)
+    });
+
+    let mut missing = Vec::new();
+    for (valid_states, range_missing) in ranges {
+      for state in valid_states { self.track_unique_state(state, Classification::Valid); }
+      missing.extend(range_missing);
+    }
+    missing
+  }
+
+  pub(super) fn classify_invalid_parallel(&mut self, invalid_list: Vec<S>, progress: &Progress) {
+    let this = &*self;
+    let classifications = map_ranges(invalid_list.len(), this.threads, |range| {
+      let mut classifications = Vec::with_capacity(range.len());
+      for chunk in invalid_list[range].chunks(REPORT_INTERVAL as usize) {
+        classifications.extend(chunk.iter().map(|state| this.classify(*state)));
+        progress.advance(chunk.len() as u64);
+        if progress.is_cancelled() { break }
+      }
+      classifications
+    });
+    for (state, classification) in invalid_list.into_iter().zip(classifications.into_iter().flatten()) {
+      self.track_unique_state(state, classification);
+    }
+  }
+
+  // Joins every state with the ones its reversible moves reach, which gives the bubbles but not their order.
+  // Then each bubble gets searched from its first state, like the serial search would
+  pub(super) fn identify_bubbles_parallel(&mut self, progress: &Progress) {
+    // Where each list starts in self.states.iter().flatten()
+    let starts = self.states.iter()
+      .scan(0, |start, list| Some(replace(start, *start + list.len())))
+      .collect::<Vec<_>>()
+    ;
+    let position = |state: S| {
+      let (list, idx) = self.meta.classification(self.ops.rank(state)).unwrap();
+      (starts[list] + idx) as u32
+    };
+
+    let sets = UnionFind::new(self.states.iter().map(Vec::len).sum());
+    for (list, start) in self.states.iter().zip(&starts) {
+      map_ranges(list.len(), self.threads, |range| {
+        let mut from = start + range.start;
+        for chunk in list[range].chunks(REPORT_INTERVAL as usize) {
+          for state in chunk {
+            for center in 0 .. self.length() {
+              for operation in [-1, 1] {
+                let Some(new_state) = self.splash_state(*state, center, operation, true) else { continue };
+                sets.union(from as u32, position(self.canonical(new_state)));
+              }
+            }
+            from += 1;
+          }
+          progress.advance(chunk.len() as u64);
+          if progress.is_cancelled() { return }
+        }
+      });
+      if progress.is_cancelled() { return }
+    }
+
+    // A bubble's first state is the root of its set
+    let roots = self.states.iter().zip(&starts)
+      .flat_map(|(list, start)| list.iter().enumerate().map(move |(idx, state)| ((start + idx) as u32, *state)))
+      .filter(|(position, _)| sets.find(*position) == *position)
+      .map(|(_, state)| state)
+      .collect::<Vec<_>>()
+    ;
+    drop(sets);
+
+    // Bubbles can be any size, so threads take the next root whenever they're free
+    let seen = AtomicBitset::new(self.meta.len());
+    let next_root = AtomicUsize::new(0);
+    let found = map_ranges(self.threads, self.threads, |_| {
+      let mut found = Vec::new();
+      let mut seen = &seen;
+      loop {
+        let root = next_root.fetch_add(1, Relaxed);
+        if root >= roots.len() || progress.is_cancelled() { break }
+        let mut bubble = Vec::new();
+        self.dfs(roots[root], true, &mut seen, progress, |state| bubble.push(state));
+        found.push((root, bubble));
+      }
+      found
+    });
+    if progress.is_cancelled() { return }
+
+    let mut bubbles = vec![Vec::new(); roots.len()];
+    for (root, bubble) in found.into_iter().flatten() { bubbles[root] = bubble }
+    let mut smol_bubbles = Vec::new();
+    for bubble in bubbles {
+      if bubble.len() == 1 { smol_bubbles.extend(bubble) }
+      else { self.bubbles.push(bubble) }
+    }
+    self.index_bubbles(smol_bubbles);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::{Boundary, Encoding, MoveRule, Neighborhood, Progress, StateData};
+
+  fn explore(neighbors: &[Vec<usize>], maxes: &[u8], rule: MoveRule, orbits: bool, threads: usize) -> StateData<u64> {
+    StateData::new(neighbors.to_vec(), maxes.to_vec(), rule, Encoding::BitPacked, orbits, Some(threads), &Progress::default())
+      .unwrap()
+  }
+
+  #[test]
+  fn parallel_matches_serial() {
+    let graphs = [
+      // Path, cycle and star
+      vec![vec![1], vec![0, 2], vec![1, 3], vec![2, 4], vec![3]],
+      vec![vec![1, 4], vec![0, 2], vec![1, 3], vec![2, 4], vec![3, 0]],
+      vec![vec![1, 2, 3, 4], vec![0], vec![0], vec![0], vec![0]],
+      // A directed cycle with a chord
+      vec![vec![1], vec![2], vec![3, 0], vec![4], vec![0]],
+    ];
+    let rules = [
+      MoveRule::default(),
+      MoveRule { neighborhood: Neighborhood::Open, ..MoveRule::default() },
+      MoveRule { center_delta: 2, boundary: Boundary::Wrap, ..MoveRule::default() },
+      MoveRule { neighbor_delta: -1, boundary: Boundary::Reject, ..MoveRule::default() },
+    ];
+    for neighbors in &graphs {
+      for maxes in [vec![2; 5], vec![1, 2, 3, 2, 1]] {
+        for rule in rules {
+          for orbits in [false, true] {
+            let context = format!("{neighbors:?} with maxes {maxes:?}, {rule}, orbits {orbits}");
+            let serial = explore(neighbors, &maxes, rule, orbits, 1);
+            let parallel = explore(neighbors, &maxes, rule, orbits, 3);
+            assert_eq!(serial.states, parallel.states, "lists of {context}");
+            assert_eq!(serial.bubbles, parallel.bubbles, "bubbles of {context}");
+            let (serial, parallel) = (&serial.dag, &parallel.dag);
+            assert_eq!(serial.exits, parallel.exits, "DAG of {context}");
+            assert_eq!(serial.groups, parallel.groups, "DAG groups of {context}");
+            assert_eq!(serial.longest_chain, parallel.longest_chain, "longest chain of {context}");
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,427 @@
+use ahash::AHashMap;
+
+use crate::graph;
+
+use super::theorem::{self, Theorem};
+use super::{
+  AnyState, BubbleDag, Cache, Classification, Encoding, MoveRule, PackedState, Progress, StateOps, StateSpace,
+  explore, neighborhood_zero_or_max, splash_state,
+};
+
+struct Component {
+  // Index of each of the component's nodes in the whole graph
+  nodes: Vec<usize>,
+  space: Box<dyn StateSpace>,
+  // States grouped by whether they're valid and which theorems hold on them,
+  // which is all the classification of the whole graph depends on
+  groups: Vec< Vec<AnyState> >,
+  // state -> (group, idx)
+  locations: AHashMap<AnyState, (usize, usize)>,
+}
+impl Component {
+  // Bubble size of a component bubble id, every single state counts as its own bubble
+  fn bubble_len(&self, id: usize) -> usize {
+    let dag = self.space.bubble_dag();
+    if dag.is_single(id) { 1 } else { self.space.bubble_len(id) }
+  }
+
+  fn bubble_state(&self, id: usize, idx: usize) -> Option<AnyState> {
+    let (bubble_idx, state_idx) = self.space.bubble_dag().location(id);
+    if self.space.bubble_dag().is_single(id) {
+      if idx > 0 { return None }
+      self.space.bubble_state(bubble_idx, state_idx)
+    } else { self.space.bubble_state(bubble_idx, idx) }
+  }
+}
+
+// Every state whose components are in these groups, they all share a classification
+struct Block {
+  groups: Vec<usize>,
+  classification: Classification,
+  // Where the block starts in its classification's list
+  offset: usize,
+}
+
+/// State space of a disconnected graph, as the product of its components' state spaces.
+///
+/// Moves only touch the closed neighborhood of a node, so each component moves on its own.
+/// A state is valid when every component is, its bubble is the product of its components' bubbles,
+/// and theorems combine their components with [Theorem::on_components].
+/// Only the components get explored and states are never listed out,
+/// but the bubble DAG has a bubble for every combination of the components' bubbles.
+pub struct ProductData<S: PackedState> {
+  components: Vec<Component>,
+  // One per combination of groups, as mixed radix numbers with the first component lowest
+  blocks: Vec<Block>,
+  // Blocks in each classification's list, indexed like StateData's lists
+  lists: Vec< Vec<usize> >,
+  list_lens: Vec<usize>,
+  // Product bubble id -> combination of component bubble ids, and back
+  bubble_ids: Vec<usize>,
+  id_of: Vec<usize>,
+  big_bubbles: usize,
+  dag: BubbleDag,
+  theorems: Vec< Box<dyn Theorem<S>> >,
+  compositions: Vec<fn(&[bool]) -> bool>,
+  // One more than the largest max
+  base: u8,
+  ops: StateOps,
+  rule: MoveRule,
+  neighbors: Vec< Vec<usize> >,
+}
+impl<S: PackedState> ProductData<S> {
+  /// The nodes of each component, None if the graph is connected,
+  /// a theorem can't be combined over components, or there are too many states to count
+  pub fn factors(neighbors: &[Vec<usize>], maxes: &[u8], rule: MoveRule) -> Option< Vec< Vec<usize> > > {
+    if theorem::registered::<S>(rule).iter().any(|theorem| theorem.on_components().is_none()) { return None }
+    maxes.iter().try_fold(1usize, |total, max| total.checked_mul(*max as usize + 1))?;
+
+    // Arcs tie their ends together whichever way they point
+    let neighbors = graph::underlying(neighbors);
+    let mut seen = vec![false; neighbors.len()];
+    let mut components = Vec::new();
+    for root in 0 .. neighbors.len() {
+      if seen[root] { continue }
+      seen[root] = true;
+      let mut component = vec![root];
+      let mut next = 0;
+      while let Some(node) = component.get(next).copied() {
+        next += 1;
+        for neighbor in &neighbors[node] {
+          if !seen[*neighbor] { seen[*neighbor] = true; component.push(*neighbor) }
+        }
+      }
+      component.sort_unstable();
+      components.push(component);
+    }
+    (components.len() > 1).then_some(components)
+  }
+
+  /// Explores every component with [explore], `cache` and `threads` included, None if `progress` gets cancelled
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    neighbors: Vec< Vec<usize> >, components: Vec< Vec<usize> >, maxes: Vec<u8>, rule: MoveRule, encoding: Encoding,
+    cache: Option<&Cache>, threads: Option<usize>, progress: &Progress,
+  ) -> Option<Self> {
+    let theorems = theorem::registered::<S>(rule);
+    let compositions = theorems.iter().map(|theorem| theorem.on_components()).collect::<Option<Vec<_>>>()?;
+
+    let mut local = vec![0; neighbors.len()];
+    for nodes in &components {
+      for (idx, node) in nodes.iter().enumerate() { local[*node] = idx }
+    }
+    let mut explored = Vec::with_capacity(components.len());
+    for nodes in components {
+      let component_neighbors = nodes.iter()
+        .map(|node| neighbors[*node].iter().map(|neighbor| local[*neighbor]).collect())
+        .collect()
+      ;
+      let component_maxes = nodes.iter().map(|node| maxes[*node]).collect();
+      let space = explore(component_neighbors, component_maxes, rule, encoding, false, cache, threads, progress)?;
+      explored.push(Self::group(nodes, space));
+    }
+
+    let mut data = Self {
+      components: explored,
+      blocks: Vec::new(),
+      lists: vec![Vec::new(); theorems.len() + 2],
+      list_lens: vec![0; theorems.len() + 2],
+      bubble_ids: Vec::new(),
+      id_of: Vec::new(),
+      big_bubbles: 0,
+      dag: BubbleDag::new(0, Vec::new()),
+      theorems,
+      compositions,
+      base: maxes.iter().max().unwrap() + 1,
+      ops: StateOps::new(maxes.iter().map(|max| max + 1).collect(), encoding),
+      rule,
+      neighbors,
+    };
+    data.build_blocks();
+    data.build_bubbles();
+    Some(data)
+  }
+
+  fn group(nodes: Vec<usize>, space: Box<dyn StateSpace>) -> Component {
+    let theorem_count = space.theorem_names().len();
+    let mut classifications = vec![Classification::Valid, Classification::InvalidOther];
+    classifications.extend((0 .. theorem_count).map(Classification::InvalidTheorem));
+
+    let mut profiles = AHashMap::new();
+    let mut groups: Vec< Vec<AnyState> > = Vec::new();
+    let mut locations = AHashMap::new();
+    for classification in classifications {
+      for idx in 0 .. space.list_len(classification) {
+        let state = space.list_state(classification, idx).unwrap();
+        let holds = (0 .. theorem_count).map(|theorem| space.theorem_holds(theorem, state)).collect::<Vec<_>>();
+        let next = profiles.len();
+        let group = *profiles.entry((classification == Classification::Valid, holds)).or_insert(next);
+        if group == groups.len() { groups.push(Vec::new()) }
+        locations.insert(state, (group, groups[group].len()));
+        groups[group].push(state);
+      }
+    }
+    Component { nodes, space, groups, locations }
+  }
+
+  fn build_blocks(&mut self) {
+    let radices = self.components.iter().map(|component| component.groups.len()).collect::<Vec<_>>();
+    let combinations = radices.iter().product();
+    for combination in 0 .. combinations {
+      let groups = mixed_radix(combination, &radices);
+      let classification = self.classify(|component, theorem| {
+        let group = &self.components[component].groups[groups[component]];
+        let state = group[0];
+        match theorem {
+          None => self.components[component].space.classification_data(state).0 == Classification::Valid,
+          Some(theorem) => self.components[component].space.theorem_holds(theorem, state),
+        }
+      });
+      let len = groups.iter().zip(&self.components)
+        .map(|(group, component)| component.groups[*group].len())
+        .product::<usize>()
+      ;
+
+      let list = self.list_idx(classification);
+      self.lists[list].push(self.blocks.len());
+      self.blocks.push(Block { groups, classification, offset: self.list_lens[list] });
+      self.list_lens[list] += len;
+    }
+  }
+
+  // `component_holds(component, None)` is whether the component is valid, with Some(theorem) whether it holds
+  fn classify(&self, component_holds: impl Fn(usize, Option<usize>) -> bool) -> Classification {
+    let components = 0 .. self.components.len();
+    if components.clone().all(|component| component_holds(component, None)) { return Classification::Valid }
+    (0 .. self.theorems.len())
+      .find(|theorem| {
+        let holds = components.clone().map(|component| component_holds(component, Some(*theorem))).collect::<Vec<_>>();
+        (self.compositions[*theorem])(&holds)
+      })
+      .map_or(Classification::InvalidOther, Classification::InvalidTheorem)
+  }
+
+  // A product bubble is big unless every component's bubble is a single state, big ones get ids first.
+  // Every combination gets an id and its exits, so this takes time and memory in the product of the components' bubble counts
+  fn build_bubbles(&mut self) {
+    let radices = self.components.iter()
+      .map(|component| component.space.bubble_dag().exits.len())
+      .collect::<Vec<_>>()
+    ;
+    let combinations = radices.iter().product::<usize>();
+    let is_single = |combination: usize| {
+      mixed_radix(combination, &radices).into_iter().zip(&self.components)
+        .all(|(id, component)| component.space.bubble_dag().is_single(id))
+    };
+    let (big, single): (Vec<usize>, Vec<usize>) = (0 .. combinations).partition(|combination| !is_single(*combination));
+    self.big_bubbles = big.len();
+    self.bubble_ids = big;
+    self.bubble_ids.extend(single);
+    self.id_of = vec![0; combinations];
+    for (id, combination) in self.bubble_ids.iter().enumerate() { self.id_of[*combination] = id }
+
+    // A move only changes one component's bubble
+    let mut exits = Vec::with_capacity(combinations);
+    for combination in &self.bubble_ids {
+      let ids = mixed_radix(*combination, &radices);
+      let mut bubble_exits = Vec::new();
+      let mut stride = 1;
+      for ((id, component), radix) in ids.iter().zip(&self.components).zip(&radices) {
+        for exit in &component.space.bubble_dag().exits[*id] {
+          bubble_exits.push(self.id_of[combination - id * stride + exit * stride]);
+        }
+        stride *= radix;
+      }
+      bubble_exits.sort_unstable();
+      bubble_exits.dedup();
+      exits.push(bubble_exits);
+    }
+    self.dag = BubbleDag::new(self.big_bubbles, exits);
+  }
+
+  fn list_idx(&self, classification: Classification) -> usize {
+    match classification {
+      Classification::Valid => 0,
+      Classification::InvalidTheorem(theorem) => theorem + 1,
+      Classification::InvalidOther => self.theorems.len() + 1,
+    }
+  }
+
+  pub fn length(&self) -> usize { self.neighbors.len() }
+
+  // The state of each component
+  fn split(&self, state: S) -> Vec<AnyState> {
+    let values = self.ops.unpack(state);
+    self.components.iter()
+      .map(|component| component.space.parse_vec(component.nodes.iter().map(|node| values[*node]).collect()))
+      .collect()
+  }
+
+  fn join(&self, states: impl IntoIterator<Item = AnyState>) -> S {
+    let mut values = vec![0; self.length()];
+    for (component, state) in self.components.iter().zip(states) {
+      for (node, value) in component.nodes.iter().zip(component.space.parse_state(state)) { values[*node] = value }
+    }
+    self.ops.pack(values)
+  }
+
+  pub fn classification_data(&self, state: S) -> (Classification, usize) {
+    let mut combination = 0;
+    let mut stride = 1;
+    let mut idx = 0;
+    let mut idx_stride = 1;
+    for (component, state) in self.components.iter().zip(self.split(state)) {
+      let (group, group_idx) = component.locations[&state];
+      combination += group * stride;
+      stride *= component.groups.len();
+      idx += group_idx * idx_stride;
+      idx_stride *= component.groups[group].len();
+    }
+    let block = &self.blocks[combination];
+    (block.classification, block.offset + idx)
+  }
+
+  pub fn list_state(&self, classification: Classification, idx: usize) -> Option<S> {
+    let list_idx = self.list_idx(classification);
+    if idx >= self.list_lens[list_idx] { return None }
+    let list = &self.lists[list_idx];
+    let block = &self.blocks[list[list.partition_point(|block| self.blocks[*block].offset <= idx) - 1]];
+
+    let mut idx = idx - block.offset;
+    let states = self.components.iter().zip(&block.groups).map(|(component, group)| {
+      let group = &component.groups[*group];
+      let state = group[idx % group.len()];
+      idx /= group.len();
+      state
+    }).collect::<Vec<_>>();
+    Some(self.join(states))
+  }
+
+  /// Returns (bubble_idx, state_idx)
+  pub fn bubble_data(&self, state: S) -> (usize, usize) {
+    let mut combination = 0;
+    let mut stride = 1;
+    let mut idx = 0;
+    let mut idx_stride = 1;
+    for (component, state) in self.components.iter().zip(self.split(state)) {
+      let dag = component.space.bubble_dag();
+      let (bubble_idx, state_idx) = component.space.bubble_data(state);
+      let id = dag.id(bubble_idx, state_idx);
+      combination += id * stride;
+      stride *= dag.exits.len();
+      if !dag.is_single(id) { idx += state_idx * idx_stride }
+      idx_stride *= component.bubble_len(id);
+    }
+    let id = self.id_of[combination];
+    if id < self.big_bubbles { (id, idx) } else { self.dag.location(id) }
+  }
+
+  pub fn bubble_len(&self, bubble: usize) -> usize {
+    if bubble == self.big_bubbles { return self.bubble_ids.len() - self.big_bubbles }
+    self.bubble_components(bubble).iter().zip(&self.components)
+      .map(|(id, component)| component.bubble_len(*id))
+      .product()
+  }
+
+  pub fn bubble_state(&self, bubble: usize, idx: usize) -> Option<S> {
+    let (id, mut idx) = if bubble == self.big_bubbles { (self.big_bubbles + idx, 0) } else { (bubble, idx) };
+    if id >= self.bubble_ids.len() { return None }
+    let states = self.bubble_components(id).into_iter().zip(&self.components).map(|(id, component)| {
+      let len = component.bubble_len(id);
+      let state = component.bubble_state(id, idx % len);
+      idx /= len;
+      state
+    }).collect::<Option<Vec<_>>>()?;
+    if idx > 0 { return None }
+    Some(self.join(states))
+  }
+
+  // The component bubble ids of a product bubble id
+  fn bubble_components(&self, id: usize) -> Vec<usize> {
+    let radices = self.components.iter()
+      .map(|component| component.space.bubble_dag().exits.len())
+      .collect::<Vec<_>>()
+    ;
+    mixed_radix(self.bubble_ids[id], &radices)
+  }
+
+  /// Solves each component on its own, see [super::StateData::solve]
+  pub fn solve(&self, from: S, to: S, reversible_only: bool) -> Option< Vec<(usize, i8)> > {
+    let mut moves = Vec::new();
+    for ((component, from), to) in self.components.iter().zip(self.split(from)).zip(self.split(to)) {
+      let solution = component.space.solve(from, to, reversible_only)?;
+      moves.extend(solution.into_iter().map(|(center, operation)| (component.nodes[center], operation)));
+    }
+    Some(moves)
+  }
+}
+impl<S: PackedState> StateSpace for ProductData<S> {
+  fn base(&self) -> u8 { self.base }
+  fn bases(&self) -> &[u8] { &self.ops.bases }
+  fn rule(&self) -> MoveRule { self.rule }
+  fn length(&self) -> usize { self.length() }
+
+  fn parse_state(&self, state: AnyState) -> Vec<u8> { self.ops.unpack(S::narrow(state)) }
+  fn parse_vec(&self, vec: Vec<u8>) -> AnyState { self.ops.pack::<S>(vec).widen() }
+  fn set_packed(&self, state: AnyState, idx: usize, value: u8) -> AnyState {
+    self.ops.set(S::narrow(state), idx, value).widen()
+  }
+  fn splash_state(&self, state: AnyState, center: usize, operation: i8, reversible_only: bool) -> Option<AnyState> {
+    splash_state(&self.neighbors, &self.ops, &self.rule, S::narrow(state), center, operation, reversible_only).map(S::widen)
+  }
+  fn neighborhood_zero_or_max(&self, state: AnyState, node: usize) -> (bool, bool) {
+    neighborhood_zero_or_max(&self.neighbors, &self.ops, &self.rule, S::narrow(state), node)
+  }
+  fn solve(&self, from: AnyState, to: AnyState, reversible_only: bool) -> Option< Vec<(usize, i8)> > {
+    self.solve(S::narrow(from), S::narrow(to), reversible_only)
+  }
+
+  fn classification_data(&self, state: AnyState) -> (Classification, usize) {
+    self.classification_data(S::narrow(state))
+  }
+  fn theorem_names(&self) -> Vec<&str> { self.theorems.iter().map(|theorem| theorem.name()).collect() }
+  fn theorem_holds(&self, theorem: usize, state: AnyState) -> bool {
+    let holds = self.components.iter().zip(self.split(S::narrow(state)))
+      .map(|(component, state)| component.space.theorem_holds(theorem, state))
+      .collect::<Vec<_>>()
+    ;
+    (self.compositions[theorem])(&holds)
+  }
+  fn list_len(&self, classification: Classification) -> usize { self.list_lens[self.list_idx(classification)] }
+  fn list_state(&self, classification: Classification, idx: usize) -> Option<AnyState> {
+    self.list_state(classification, idx).map(S::widen)
+  }
+  fn frequency_analysis(&self, classifications: &[Classification]) -> Vec< Vec<u32> > {
+    let mut result = Vec::new();
+    for classification in classifications {
+      for idx in 0 .. self.list_len(*classification) {
+        let mut count = vec![0; self.base as usize];
+        for value in self.ops.unpack(self.list_state(*classification, idx).unwrap()) {
+          count[value as usize] += 1;
+        }
+        result.push(count);
+      }
+    }
+    result
+  }
+
+  fn bubble_data(&self, state: AnyState) -> (usize, usize) { self.bubble_data(S::narrow(state)) }
+  fn bubble_count(&self) -> usize { self.big_bubbles + 1 }
+  fn bubble_len(&self, bubble: usize) -> usize { self.bubble_len(bubble) }
+  fn bubble_state(&self, bubble: usize, idx: usize) -> Option<AnyState> {
+    self.bubble_state(bubble, idx).map(S::widen)
+  }
+  fn bubble_dag(&self) -> &BubbleDag { &self.dag }
+
+  fn canonical(&self, state: AnyState) -> AnyState { state }
+  fn orbit_size(&self, _state: AnyState) -> Option<usize> { None }
+}
+
+// Digits of `number`, lowest first
+fn mixed_radix(mut number: usize, radices: &[usize]) -> Vec<usize> {
+  radices.iter().map(|radix| {
+    let digit = number % radix;
+    number /= radix;
+    digit
+  }).collect()
+}
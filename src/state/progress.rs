@@ -0,0 +1,97 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering::Relaxed};
+use std::thread::{self, JoinHandle};
+
+use crate::graph::Graph;
+use super::{Cache, Encoding, MoveRule, StateSpace, explore};
+
+/// How many states a phase works through between progress reports and cancellation checks
+pub(super) const REPORT_INTERVAL: u64 = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Phase {
+  GenerateValid,
+  GenerateInvalid,
+  ClassifyInvalid,
+  IdentifyBubbles,
+  CondenseBubbles,
+}
+impl Display for Phase {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let str = match self {
+      Self::GenerateValid => "Generating valid states",
+      Self::GenerateInvalid => "Generating invalid states",
+      Self::ClassifyInvalid => "Classifying invalid states",
+      Self::IdentifyBubbles => "Identifying bubbles",
+      Self::CondenseBubbles => "Condensing bubbles",
+    };
+    write!(f, "{}", str)
+  }
+}
+
+/// Shared between an exploration and whoever is watching it
+#[derive(Default)]
+pub struct Progress {
+  phase: AtomicU8,
+  done: AtomicU64,
+  total: AtomicU64,
+  cancelled: AtomicBool,
+}
+impl Progress {
+  /// A total of 0 means we don't know how much work the phase is
+  pub fn start(&self, phase: Phase, total: u64) {
+    self.phase.store(phase as u8, Relaxed);
+    self.done.store(0, Relaxed);
+    self.total.store(total, Relaxed);
+  }
+
+  pub fn advance(&self, amount: u64) { self.done.fetch_add(amount, Relaxed); }
+
+  pub fn cancel(&self) { self.cancelled.store(true, Relaxed) }
+
+  pub fn is_cancelled(&self) -> bool { self.cancelled.load(Relaxed) }
+
+  /// Returns (phase, done, total)
+  pub fn status(&self) -> (Phase, u64, u64) {
+    let phase = match self.phase.load(Relaxed) {
+      0 => Phase::GenerateValid,
+      1 => Phase::GenerateInvalid,
+      2 => Phase::ClassifyInvalid,
+      3 => Phase::IdentifyBubbles,
+      _ => Phase::CondenseBubbles,
+    };
+    (phase, self.done.load(Relaxed), self.total.load(Relaxed))
+  }
+}
+
+/// Explores a state space on a worker thread
+pub struct Exploration {
+  progress: Arc<Progress>,
+  worker: JoinHandle< Option< Box<dyn StateSpace> > >,
+}
+impl Exploration {
+  /// See [explore] for `encoding`, `orbits` and `cache`, the number of threads is picked by the number of states
+  pub fn start(graph: &mut Graph, max: u8, rule: MoveRule, encoding: Encoding, orbits: bool, cache: Option<Cache>) -> Self {
+    graph.contiguize_and_trim();
+    let neighbors = graph.get_neighbors();
+    let maxes = graph.get_maxes(max);
+    let progress = Arc::new(Progress::default());
+    let worker_progress = progress.clone();
+    let worker = thread::spawn(move || explore(neighbors, maxes, rule, encoding, orbits, cache.as_ref(), None, &worker_progress));
+    Self { progress, worker }
+  }
+
+  pub fn progress(&self) -> &Progress { &self.progress }
+
+  pub fn cancel(&self) { self.progress.cancel() }
+
+  pub fn is_finished(&self) -> bool { self.worker.is_finished() }
+
+  /// Blocks until the worker is done, None if it was cancelled, the graph was empty or had too many states.
+  /// If the worker panicked, the panic carries on in the caller
+  pub fn join(self) -> Option< Box<dyn StateSpace> > {
+    self.worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+  }
+}
@@ -0,0 +1,108 @@
+use std::fmt::Display;
+
+/// Which nodes a move on a center touches
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub enum Neighborhood {
+  /// Only the center's neighbors
+  Open,
+  /// The center's neighbors and the center itself
+  #[default]
+  Closed,
+}
+impl Display for Neighborhood {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Open => write!(f, "Open"),
+      Self::Closed => write!(f, "Closed"),
+    }
+  }
+}
+
+/// What a move does to a value that would leave 0 ..= max
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub enum Boundary {
+  /// Values stop at 0 and max, the original rule
+  #[default]
+  Clamp,
+  /// Values wrap around mod max + 1 like in Lights Out, so every move can be undone.
+  /// Validity becomes linear algebra, see [super::ModularSolver]
+  Wrap,
+  /// The whole move isn't allowed
+  Reject,
+}
+impl Boundary {
+  pub const ALL: [Self; 3] = [Self::Clamp, Self::Wrap, Self::Reject];
+}
+impl Display for Boundary {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Clamp => write!(f, "Clamp"),
+      Self::Wrap => write!(f, "Wrap"),
+      Self::Reject => write!(f, "Reject"),
+    }
+  }
+}
+
+/// How a move on a center changes the nodes around it, the default is the original rule.
+/// A move with operation ±1 adds ±delta to every node it touches
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct MoveRule {
+  pub neighborhood: Neighborhood,
+  // Only used with a closed neighborhood
+  pub center_delta: i8,
+  pub neighbor_delta: i8,
+  pub boundary: Boundary,
+}
+impl Default for MoveRule {
+  fn default() -> Self {
+    Self { neighborhood: Neighborhood::Closed, center_delta: 1, neighbor_delta: 1, boundary: Boundary::Clamp }
+  }
+}
+impl Display for MoveRule {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.neighborhood {
+      Neighborhood::Open => write!(f, "open neighborhood, neighbors {:+}", self.neighbor_delta)?,
+      Neighborhood::Closed => write!(
+        f, "closed neighborhood, center {:+}, neighbors {:+}", self.center_delta, self.neighbor_delta
+      )?,
+    }
+    write!(f, ", {}", self.boundary.to_string().to_lowercase())
+  }
+}
+impl MoveRule {
+  /// Every node a move on `center` changes, with its delta
+  pub fn affected<'a>(&self, neighbors: &'a [Vec<usize>], center: usize) -> impl Iterator<Item = (usize, i8)> + 'a {
+    let center_delta = match self.neighborhood {
+      Neighborhood::Open => 0,
+      Neighborhood::Closed => self.center_delta,
+    };
+    let neighbor_delta = self.neighbor_delta;
+    neighbors[center].iter().map(move |neighbor| (*neighbor, neighbor_delta))
+      .chain([(center, center_delta)])
+      .filter(|(_, delta)| *delta != 0)
+  }
+
+  /// The new value of a node, and whether the move can be undone as far as it's concerned.
+  /// None if the move isn't allowed
+  pub fn apply(&self, value: u8, delta: i8, operation: i8, base: u8) -> Option<(u8, bool)> {
+    let target = value as i16 + delta as i16 * operation as i16;
+    let base = base as i16;
+    match self.boundary {
+      Boundary::Clamp => {
+        let clamped = target.clamp(0, base - 1);
+        Some((clamped as u8, clamped == target))
+      }
+      Boundary::Wrap => Some((target.rem_euclid(base) as u8, true)),
+      Boundary::Reject => (0 .. base).contains(&target).then_some((target as u8, true)),
+    }
+  }
+
+  /// Whether every node a move touches goes the same way, which Theorem 1 relies on
+  pub fn is_monotone(&self) -> bool {
+    let center = match self.neighborhood {
+      Neighborhood::Open => 0,
+      Neighborhood::Closed => self.center_delta,
+    };
+    (center as i16 * self.neighbor_delta as i16) >= 0
+  }
+}
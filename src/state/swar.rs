@@ -0,0 +1,182 @@
+use std::mem::replace;
+
+use super::{Boundary, Encoding, MoveRule, PackedState, StateOps};
+
+/// Applies a move to every digit it touches at once with word-wide arithmetic, instead of a get and set per node.
+///
+/// Each move is split into passes over every other digit, so the digit above each one in a pass is free
+/// to catch its carry or borrow, which flags the digits that went past 0 or max in the same add.
+/// Amounts are capped at max beforehand so nothing carries further than that.
+/// Only works with [Encoding::BitPacked] when the state has a spare bit above its top digit.
+pub struct Swar<S: PackedState> {
+  // Indexed by center * 2, plus 1 for +1 moves
+  moves: Vec< Move<S> >,
+  boundary: Boundary,
+}
+
+struct Move<S: PackedState> {
+  passes: Vec< Pass<S> >,
+  // Touches a node whose max is 0, which always saturates
+  saturates: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+  Add,
+  Subtract,
+  Wrap,
+}
+
+struct Pass<S: PackedState> {
+  kind: Kind,
+  // Which of every other digit, see Swar::new
+  half: usize,
+  // Bits of every digit in the pass
+  digits: S,
+  // How much each digit changes, capped at its max
+  amounts: S,
+  // Added to the sum so the digits past their limit carry into their flag, 2^width - 1 - max when adding
+  // and 2^width - base when wrapping
+  offsets: S,
+  maxes: S,
+  // The bit right above each digit
+  flags: S,
+  // Flags of digits that change by more than their max, which saturate whatever their value
+  always: S,
+  // Flags of the digits of each width, with the width
+  widths: Vec<(S, usize)>,
+}
+impl<S: PackedState> Pass<S> {
+  fn new(kind: Kind, half: usize) -> Self {
+    Self {
+      kind,
+      half,
+      digits: S::default(),
+      amounts: S::default(),
+      offsets: S::default(),
+      maxes: S::default(),
+      flags: S::default(),
+      always: S::default(),
+      widths: Vec::new(),
+    }
+  }
+
+  fn add_digit(&mut self, offset: usize, width: usize, base: u8, amount: u8) {
+    let max = base - 1;
+    let shifted = |value: u8| S::from_digit(value) << offset;
+    let flag = S::from_digit(1) << (offset + width);
+    self.digits = self.digits | shifted(((1u16 << width) - 1) as u8);
+    self.amounts = self.amounts | shifted(amount.min(max));
+    self.offsets = self.offsets | match self.kind {
+      Kind::Add => shifted(((1u16 << width) - 1) as u8 - max),
+      Kind::Wrap => shifted(((1u16 << width) - base as u16) as u8),
+      Kind::Subtract => S::default(),
+    };
+    self.maxes = self.maxes | shifted(max);
+    self.flags = self.flags | flag;
+    if amount > max { self.always = self.always | flag }
+    match self.widths.iter_mut().find(|(_, class)| *class == width) {
+      Some((flags, _)) => *flags = *flags | flag,
+      None => self.widths.push((flag, width)),
+    }
+  }
+
+  // Every bit of each digit whose flag is set
+  fn spread(&self, flags: S) -> S {
+    self.widths.iter().fold(S::default(), |mask, (class, width)| {
+      let flags = flags & *class;
+      mask | flags.wrapping_sub(flags >> *width)
+    })
+  }
+
+  /// The new digits, and whether any of them saturated
+  fn apply(&self, state: S) -> (S, bool) {
+    let digits = state & self.digits;
+    match self.kind {
+      Kind::Add => {
+        let sum = digits.wrapping_add(self.amounts);
+        let saturated = (sum.wrapping_add(self.offsets) & self.flags) | self.always;
+        let mask = self.spread(saturated);
+        (((sum & !mask) | (self.maxes & mask)) & self.digits, saturated != S::default())
+      }
+      Kind::Subtract => {
+        let difference = (digits | self.flags).wrapping_sub(self.amounts);
+        let borrowed = (!difference & self.flags) | self.always;
+        (difference & self.digits & !self.spread(borrowed), borrowed != S::default())
+      }
+      Kind::Wrap => {
+        let sum = digits.wrapping_add(self.amounts);
+        let wrapped = sum.wrapping_add(self.offsets);
+        let mask = self.spread(wrapped & self.flags);
+        (((wrapped & mask) | (sum & !mask)) & self.digits, false)
+      }
+    }
+  }
+}
+
+impl<S: PackedState> Swar<S> {
+  /// None if `ops` doesn't fit, see [Swar]
+  pub fn new(neighbors: &[Vec<usize>], ops: &StateOps, rule: MoveRule) -> Option<Self> {
+    if ops.encoding != Encoding::BitPacked || ops.bits() >= S::BITS { return None }
+
+    // Digits of width 0 take no bits, so they're skipped when alternating
+    let mut halves = vec![0; ops.length()];
+    let mut next = 0;
+    for (node, half) in halves.iter_mut().enumerate() {
+      if StateOps::bits_per_digit(ops.base(node)) == 0 { continue }
+      *half = next;
+      next ^= 1;
+    }
+
+    let mut moves = Vec::with_capacity(neighbors.len() * 2);
+    for center in 0 .. neighbors.len() {
+      for operation in [-1, 1] {
+        let mut passes = Vec::<Pass<S>>::new();
+        let mut saturates = false;
+        let mut touched = vec![false; neighbors.len()];
+        for (node, delta) in rule.affected(neighbors, center) {
+          // A digit can only be in one pass
+          if replace(&mut touched[node], true) { return None }
+          let (base, offset) = (ops.base(node), ops.offsets[node]);
+          let width = StateOps::bits_per_digit(base);
+          let change = delta as i16 * operation as i16;
+          let (kind, amount) = match rule.boundary {
+            Boundary::Wrap => (Kind::Wrap, change.rem_euclid(base as i16) as u8),
+            Boundary::Clamp | Boundary::Reject if change > 0 => (Kind::Add, change as u8),
+            Boundary::Clamp | Boundary::Reject => (Kind::Subtract, -change as u8),
+          };
+          if width == 0 {
+            saturates |= kind != Kind::Wrap;
+            continue;
+          }
+          if amount == 0 { continue }
+          let pass = match passes.iter().position(|pass| pass.kind == kind && pass.half == halves[node]) {
+            Some(pass) => pass,
+            None => { passes.push(Pass::new(kind, halves[node])); passes.len() - 1 }
+          };
+          passes[pass].add_digit(offset, width, base, amount);
+        }
+        moves.push(Move { passes, saturates });
+      }
+    }
+    Some(Self { moves, boundary: rule.boundary })
+  }
+
+  /// Same as [super::splash_state]
+  pub fn splash(&self, mut state: S, center: usize, operation: i8, reversible_only: bool) -> Option<S> {
+    let splash = &self.moves[center * 2 + (operation > 0) as usize];
+    let mut saturated = splash.saturates;
+    for pass in &splash.passes {
+      let (digits, pass_saturated) = pass.apply(state);
+      state = (state & !pass.digits) | digits;
+      saturated |= pass_saturated;
+    }
+    let rejected = match self.boundary {
+      Boundary::Clamp => reversible_only,
+      Boundary::Reject => true,
+      Boundary::Wrap => false,
+    };
+    if saturated && rejected { return None }
+    Some(state)
+  }
+}
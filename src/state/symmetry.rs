@@ -0,0 +1,59 @@
+use ahash::AHashSet;
+
+use crate::graph::AutomorphismGroup;
+use super::{PackedState, StateOps};
+
+/// Automorphisms of the graph acting on its states, each node's value moves to the node's image.
+/// Moves commute with automorphisms, so states in the same orbit get classified the same way.
+/// Only automorphisms keeping every node's max count, so values always fit where they land.
+pub struct Symmetry {
+  generators: Vec< Vec<usize> >,
+  ops: StateOps,
+}
+impl Symmetry {
+  /// None if the graph has no automorphisms besides the identity, or the search gave up
+  pub fn new(neighbors: &[Vec<usize>], ops: StateOps) -> Option<Self> {
+    let colors = ops.bases.iter().map(|base| *base as usize).collect::<Vec<_>>();
+    let group = AutomorphismGroup::with_colors(neighbors, &colors)?;
+    if group.generators.is_empty() { return None }
+    Some(Self { generators: group.generators, ops })
+  }
+
+  fn permute<S: PackedState>(&self, state: S, generator: &[usize]) -> S {
+    (0 .. self.ops.length()).fold(S::default(), |permuted, node| {
+      self.ops.set(permuted, generator[node], self.ops.get(state, node))
+    })
+  }
+
+  /// Every state the automorphisms take `state` to, `state` included
+  pub fn orbit<S: PackedState>(&self, state: S) -> AHashSet<S> {
+    let mut orbit = AHashSet::from_iter([state]);
+    let mut stack = vec![state];
+    while let Some(state) = stack.pop() {
+      for generator in &self.generators {
+        let image = self.permute(state, generator);
+        if orbit.insert(image) { stack.push(image) }
+      }
+    }
+    orbit
+  }
+
+  /// The smallest state in the orbit stands in for all of it
+  pub fn canonical<S: PackedState>(&self, state: S) -> S {
+    self.orbit(state).into_iter().min().unwrap()
+  }
+
+  /// Same as `self.canonical(state) == state`, but stops at the first smaller state
+  pub fn is_canonical<S: PackedState>(&self, state: S) -> bool {
+    let mut orbit = AHashSet::from_iter([state]);
+    let mut stack = vec![state];
+    while let Some(current) = stack.pop() {
+      for generator in &self.generators {
+        let image = self.permute(current, generator);
+        if image < state { return false }
+        if orbit.insert(image) { stack.push(image) }
+      }
+    }
+    true
+  }
+}
@@ -0,0 +1,58 @@
+use super::{Boundary, MoveRule, PackedState, StateData};
+
+/// A sufficient condition for a state to be invalid
+pub trait Theorem<S: PackedState>: Send + Sync {
+  fn name(&self) -> &str;
+  /// True if the theorem proves `state` can't be reached from the zero state
+  fn holds(&self, data: &StateData<S>, state: S) -> bool;
+  /// How to tell if the theorem holds on a disconnected graph from whether it holds on each component.
+  /// Disconnected graphs only get factored, see [super::explore], when every theorem has one
+  fn on_components(&self) -> Option<fn(&[bool]) -> bool> { None }
+}
+
+/// Theorems [StateData::new] tries on each invalid state, in order.
+/// Add new conjectures here to have them show up in analyze.
+/// Each theorem only gets registered under the rules it's proven for.
+pub fn registered<S: PackedState>(rule: MoveRule) -> Vec< Box<dyn Theorem<S>> > {
+  match rule.boundary {
+    Boundary::Wrap => vec![
+      Box::new(OutsideSpan),
+    ],
+    Boundary::Clamp | Boundary::Reject if rule.is_monotone() => vec![
+      Box::new(LonelyStates),
+    ],
+    Boundary::Clamp | Boundary::Reject => Vec::new(),
+  }
+}
+
+/// If every node's closed neighborhood contains a min and a max,
+/// this state is a theorem one invalid.
+/// Under other rules the neighborhood is whatever a move touches, see [MoveRule::affected]
+pub struct LonelyStates;
+impl<S: PackedState> Theorem<S> for LonelyStates {
+  fn name(&self) -> &str { "Theorem 1" }
+
+  fn holds(&self, data: &StateData<S>, state: S) -> bool {
+    for center in 0 .. data.length() {
+      let (has_zero, has_max) = data.neighborhood_zero_or_max(state, center);
+      if !(has_zero && has_max) { return false }
+    }
+    true
+  }
+
+  // Every node of every component
+  fn on_components(&self) -> Option<fn(&[bool]) -> bool> { Some(|holds| holds.iter().all(|holds| *holds)) }
+}
+
+/// When wrapping, a state is invalid exactly when the [super::ModularSolver] finds no presses for it
+pub struct OutsideSpan;
+impl<S: PackedState> Theorem<S> for OutsideSpan {
+  fn name(&self) -> &str { "Outside Span" }
+
+  fn holds(&self, data: &StateData<S>, state: S) -> bool {
+    data.solver().is_some_and(|solver| solver.presses(&data.parse_state(state)).is_none())
+  }
+
+  // Any component that can't be reached
+  fn on_components(&self) -> Option<fn(&[bool]) -> bool> { Some(|holds| holds.iter().any(|holds| *holds)) }
+}